@@ -0,0 +1,5 @@
+from .ok import helper
+
+
+def main():
+    helper()
@@ -0,0 +1,6 @@
+def parse(line):
+    return line.split(
+
+
+def other():
+    pass
@@ -0,0 +1,39 @@
+import threading
+
+LIMIT = 10
+
+
+def process(item):
+    return item * 2
+
+
+def score(item):
+    return -item
+
+
+def worker():
+    pass
+
+
+class Button:
+    def on_click(self, handler):
+        self.handler = handler
+
+    def render(self):
+        pass
+
+    def mount(self):
+        threading.Thread(target=self.render).start()
+
+
+def handler():
+    pass
+
+
+def main(xs):
+    doubled = list(map(process, xs))
+    ranked = sorted(doubled, key=score)
+    threading.Thread(target=worker).start()
+    button = Button()
+    button.on_click(handler)
+    return ranked[:LIMIT]
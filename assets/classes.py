@@ -0,0 +1,43 @@
+def save(record):
+    pass
+
+
+class Repository:
+    def __init__(self):
+        self.records = []
+
+    def add(self, record):
+        self.validate(record)
+        self.records.append(record)
+        save(record)
+
+    def validate(self, record):
+        assert record
+
+    @classmethod
+    def create(cls):
+        repo = cls.empty()
+        repo.add("first")
+        return repo
+
+    @classmethod
+    def empty(cls):
+        return cls()
+
+    class Cursor:
+        def next(self):
+            return self.peek()
+
+        def peek(self):
+            pass
+
+
+class Cache:
+    def add(self, record):
+        self.validate(record)
+
+    def validate(self, record):
+        pass
+
+
+Repository.create()
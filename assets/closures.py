@@ -0,0 +1,41 @@
+import functools
+
+
+def log(message):
+    print(message)
+
+
+def retry(times):
+    def decorator(func):
+        @functools.wraps(func)
+        def wrapper(*args, **kwargs):
+            for _ in range(times):
+                attempt()
+            return func(*args, **kwargs)
+
+        def attempt():
+            log("attempt")
+
+        return wrapper
+
+    return decorator
+
+
+def on_event(callbacks):
+    def handler(event):
+        log(event)
+        handler(event)
+
+    callbacks.append(handler)
+    return handler
+
+
+class Button:
+    def click(self):
+        def notify():
+            self.render()
+
+        notify()
+
+    def render(self):
+        pass
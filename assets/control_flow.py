@@ -0,0 +1,42 @@
+import logging
+
+
+def connect():
+    return object()
+
+
+def send(conn, message):
+    pass
+
+
+def retry(conn):
+    pass
+
+
+def close(conn):
+    pass
+
+
+class Session:
+    def __enter__(self):
+        return self
+
+    def __exit__(self, *args):
+        close(self)
+
+
+def broadcast(messages, verbose):
+    conn = connect()
+    for message in messages:
+        if verbose:
+            logging.info(message)
+        try:
+            send(conn, message)
+        except ConnectionError:
+            retry(conn)
+        finally:
+            close(conn)
+    with Session() as session:
+        send(session, "done")
+    while not send(conn, "ping"):
+        retry(conn)
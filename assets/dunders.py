@@ -0,0 +1,44 @@
+class Connection:
+    def __new__(cls, url):
+        return super().__new__(cls)
+
+    def __init__(self, url):
+        self.url = url
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, *exc):
+        return False
+
+
+class Rows:
+    def __init__(self):
+        self.items = []
+
+    def __iter__(self):
+        return iter(self.items)
+
+    def __getitem__(self, index):
+        return self.items[index]
+
+    def __len__(self):
+        return len(self.items)
+
+    def __add__(self, other):
+        return self.items + other.items
+
+
+class Plain:
+    pass
+
+
+def load(url):
+    with Connection(url) as conn:
+        for row in Rows():
+            print(row)
+        first = Rows()[0]
+        names = [str(row) for row in Rows()]
+    rows = Rows()
+    merged = rows + Rows()
+    return Plain(), first, names, len(rows), merged, conn
@@ -0,0 +1,48 @@
+def default_timeout():
+    return 30
+
+
+def make_config():
+    return {}
+
+
+def items():
+    return []
+
+
+def keep(item):
+    return True
+
+
+def transform(item):
+    return item
+
+
+def trace(func):
+    return func
+
+
+def cached(size):
+    return trace
+
+
+def schema():
+    return dict
+
+
+def connect(timeout=default_timeout()) -> schema():
+    return make_config()
+
+
+@trace
+@cached(size=make_config())
+def load(config: schema() = None):
+    return connect(timeout=default_timeout())
+
+
+def pipeline():
+    return [transform(x) for x in items() if keep(x)]
+
+
+def lookup():
+    return {key: transform(value) for key, value in items()}
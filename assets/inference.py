@@ -0,0 +1,46 @@
+from typing import Optional
+
+
+class Parser:
+    def parse(self, text):
+        return text.split()
+
+
+class Lexer:
+    def parse(self, text):
+        return list(text)
+
+
+def make_parser() -> Parser:
+    return Parser()
+
+
+def find_parser(name) -> Optional["Parser"]:
+    return None
+
+
+def run(text, lexer: Lexer, strict):
+    x = Parser()
+    x.parse(text)
+    made = make_parser()
+    made.parse(text)
+    lexer.parse(text)
+    found = find_parser("json")
+    found.parse(text)
+
+    either = Parser() if strict else None
+    either.parse(text)
+
+    mixed = None
+    if strict:
+        mixed = Parser()
+    else:
+        mixed = Lexer()
+    mixed.parse(text)
+
+    def inner():
+        return x.parse(text)
+
+    for x in []:
+        pass
+    return inner
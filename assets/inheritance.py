@@ -0,0 +1,44 @@
+from abc import ABC, abstractmethod
+
+
+class Plugin(ABC):
+    def __init__(self, name):
+        self.name = name
+
+    def start(self):
+        self.setup()
+        self.run()
+
+    def setup(self):
+        pass
+
+    @abstractmethod
+    def run(self):
+        ...
+
+
+class Logging:
+    def setup(self):
+        print("logging")
+
+
+class Exporter(Plugin):
+    def run(self):
+        self.export()
+
+    def export(self):
+        pass
+
+
+class CsvExporter(Logging, Exporter):
+    def __init__(self):
+        super().__init__("csv")
+
+    def export(self):
+        super().export()
+        self.setup()
+
+
+def main(plugin: Plugin):
+    plugin.start()
+    CsvExporter().start()
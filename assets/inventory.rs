@@ -0,0 +1,69 @@
+mod storage {
+    pub trait Store {
+        fn get(&self, key: &str) -> Option<u32>;
+
+        fn contains(&self, key: &str) -> bool {
+            self.get(key).is_some()
+        }
+    }
+
+    pub struct Memory {
+        pub items: Vec<(String, u32)>,
+    }
+
+    impl Memory {
+        pub fn new() -> Self {
+            Memory { items: Vec::new() }
+        }
+    }
+
+    impl Store for Memory {
+        fn get(&self, key: &str) -> Option<u32> {
+            self.items.iter().find(|(k, _)| k == key).map(|(_, v)| *v)
+        }
+    }
+}
+
+mod report {
+    use super::storage::{Memory, Store};
+
+    pub struct Report {
+        store: Memory,
+    }
+
+    impl Report {
+        pub fn open() -> Report {
+            Report {
+                store: Memory::new(),
+            }
+        }
+
+        pub fn line(&self, key: &str) -> String {
+            match self.store.get(key) {
+                Some(count) => format!("{key}: {}", format_count(count)),
+                None => String::from("missing"),
+            }
+        }
+    }
+
+    fn format_count(count: u32) -> String {
+        count.to_string()
+    }
+
+    pub fn total(stores: &[&dyn Store], key: &str) -> u32 {
+        stores.iter().filter_map(|store| store.get(key)).sum()
+    }
+}
+
+use report::Report;
+
+fn main() {
+    let report = Report::open();
+    for key in std::env::args().skip(1) {
+        println!("{}", report.line(&key));
+    }
+    let keys = ["a", "b"].map(|key| report.line(key));
+    if keys.is_empty() {
+        std::process::exit(1);
+    }
+}
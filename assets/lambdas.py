@@ -0,0 +1,15 @@
+def parse(line):
+    return line.split(",")
+
+
+def total(rows):
+    return sum(row[1] for row in rows)
+
+
+def pipeline(lines):
+    rows = map(lambda line: parse(line.strip()), lines)
+    ordered = sorted(rows, key=lambda row: row[0], reverse=True)
+    return total(filter(lambda row: len(row) > 1, ordered))
+
+
+handlers = [lambda: pipeline([]), lambda: print("noop")]
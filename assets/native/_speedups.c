@@ -0,0 +1,105 @@
+#define PY_SSIZE_T_CLEAN
+#include <Python.h>
+#include "vec.h"
+
+typedef double (*reducer)(const vec_t *);
+
+struct reduction {
+    const char *name;
+    reducer run;
+};
+
+static double reduce_sum(const vec_t *v)
+{
+    return vec_sum(v);
+}
+
+static double reduce_max(const vec_t *v)
+{
+    double best = v->items[0];
+    for (size_t i = 1; i < vec_len(v); i++) {
+        if (v->items[i] > best)
+            best = v->items[i];
+    }
+    return best;
+}
+
+static const struct reduction reductions[] = {
+    { .name = "sum", .run = reduce_sum },
+    { .name = "max", .run = reduce_max },
+};
+
+static int to_vec(PyObject *seq, vec_t *v)
+{
+    PyObject *item;
+    PyObject *iter = PyObject_GetIter(seq);
+    if (iter == NULL)
+        return -1;
+    while ((item = PyIter_Next(iter)) != NULL) {
+        vec_push(v, PyFloat_AsDouble(item));
+        Py_DECREF(item);
+    }
+    Py_DECREF(iter);
+    return PyErr_Occurred() ? -1 : 0;
+}
+
+static PyObject *reduce_with(PyObject *seq, const struct reduction *reduction)
+{
+    vec_t v = {0};
+    double result;
+    if (to_vec(seq, &v) < 0) {
+        vec_free(&v);
+        return NULL;
+    }
+    result = reduction->run(&v);
+    vec_free(&v);
+    return PyFloat_FromDouble(result);
+}
+
+static PyObject *fast_sum(PyObject *self, PyObject *args)
+{
+    PyObject *seq;
+    if (!PyArg_ParseTuple(args, "O", &seq))
+        return NULL;
+    return reduce_with(seq, &reductions[0]);
+}
+
+static PyObject *fast_reduce(PyObject *self, PyObject *args)
+{
+    PyObject *seq;
+    const char *name;
+    if (!PyArg_ParseTuple(args, "Os", &seq, &name))
+        return NULL;
+    for (size_t i = 0; i < sizeof reductions / sizeof *reductions; i++) {
+        if (strcmp(reductions[i].name, name) == 0)
+            return reduce_with(seq, &reductions[i]);
+    }
+    PyErr_SetString(PyExc_ValueError, name);
+    return NULL;
+}
+
+#if 0
+static PyObject *fast_mean(PyObject *self, PyObject *args)
+{
+    return PyFloat_FromDouble(vec_mean(NULL));
+}
+#endif
+
+static PyMethodDef speedups_methods[] = {
+    {"fast_sum", fast_sum, METH_VARARGS, "Sum a sequence of floats."},
+    {"fast_reduce", (PyCFunction)fast_reduce, METH_VARARGS, "Reduce a sequence by name."},
+    {}
+};
+
+static struct PyModuleDef speedups_module = {
+    PyModuleDef_HEAD_INIT,
+    "_speedups",
+    NULL,
+    -1,
+    speedups_methods
+};
+
+PyMODINIT_FUNC PyInit__speedups(void)
+{
+    return PyModule_Create(&speedups_module);
+}
@@ -0,0 +1,15 @@
+import _speedups
+
+
+def total(values):
+    return _speedups.fast_sum(values)
+
+
+def largest(values):
+    if not values:
+        raise ValueError("no values")
+    return _speedups.fast_reduce(values, "max")
+
+
+def summary(values):
+    return {"total": total(values), "max": largest(values)}
@@ -0,0 +1,59 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include "vec.h"
+
+static int grow(vec_t *v)
+{
+    size_t cap = v->cap ? v->cap * 2 : 8;
+    double *items = realloc(v->items, cap * sizeof *items);
+    if (!items)
+        return -1;
+    v->items = items;
+    v->cap = cap;
+    return 0;
+}
+
+int vec_push(vec_t *v, double x)
+{
+    if (v->len == v->cap && grow(v) < 0)
+        return -1;
+    v->items[v->len++] = x;
+    return 0;
+}
+
+double vec_sum(const vec_t *v)
+{
+    double total = 0;
+    VEC_CHECK(v);
+    for (size_t i = 0; i < vec_len(v); i++)
+        total += v->items[i];
+    return total;
+}
+
+void vec_free(vec_t *v)
+{
+    free(v->items);
+    v->items = NULL;
+    v->len = v->cap = 0;
+}
+
+void vec_check(const vec_t *v, const char *file, int line)
+{
+    if (v->len > v->cap) {
+        fprintf(stderr, "%s:%d: corrupt vector\n", file, line);
+        abort();
+    }
+}
+
+/* Old-style definition, from before the rest of the file */
+double vec_mean(v)
+    const vec_t *v;
+{
+    return vec_len(v) ? vec_sum(v) / vec_len(v) : 0;
+}
+
+void vec_apply(vec_t *v, double (*fn)(double))
+{
+    for (size_t i = 0; i < v->len; i++)
+        v->items[i] = (*fn)(v->items[i]);
+}
@@ -0,0 +1,25 @@
+#ifndef VEC_H
+#define VEC_H
+
+#include <stddef.h>
+
+typedef struct {
+    double *items;
+    size_t len;
+    size_t cap;
+} vec_t;
+
+#define VEC_CHECK(v) vec_check((v), __FILE__, __LINE__)
+
+int vec_push(vec_t *v, double x);
+double vec_sum(const vec_t *v);
+void vec_free(vec_t *v);
+void vec_check(const vec_t *v, const char *file, int line);
+void vec_apply(vec_t *v, double (*fn)(double));
+
+static inline size_t vec_len(const vec_t *v)
+{
+    return v->len;
+}
+
+#endif
@@ -0,0 +1,29 @@
+import json
+import os
+
+
+def save(record):
+    pass
+
+
+class Store:
+    def __init__(self, db):
+        self.db = db
+
+    def persist(self, record, other):
+        path = os.path.join("data", record.name)
+        self.db.save(json.dumps(record))
+        other.save(record)
+        save(path)
+        return ", ".join(load().keys())
+
+
+def load():
+    return {}
+
+
+def read(path):
+    return open(path).read()
+
+
+(load() or {}).clear()
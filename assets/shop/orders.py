@@ -0,0 +1,25 @@
+import os.path
+import numpy as np
+from collections import defaultdict as dd
+
+from . import utils
+from .utils import load as load_file
+from shop import load
+
+
+def totals(path):
+    data = load_file(os.path.join("data", path))
+    by_customer = dd(list)
+    prices = np.array(data)
+    utils.load(path)
+    load(path)
+    return by_customer, prices
+
+
+def each(items, action):
+    for item in items:
+        action(item)
+    report()
+
+
+DEFAULTS = load_file("defaults.json")
@@ -0,0 +1,10 @@
+import json
+
+
+def load(path):
+    with open(path) as f:
+        return json.load(f)
+
+
+def retry(action):
+    return action()
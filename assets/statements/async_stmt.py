@@ -0,0 +1,22 @@
+def rows():
+    return []
+
+
+def connect():
+    pass
+
+
+def handle(row):
+    pass
+
+
+def done():
+    pass
+
+
+async def consume():
+    async with connect():
+        async for row in rows():
+            handle(row)
+        else:
+            done()
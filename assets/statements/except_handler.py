@@ -0,0 +1,23 @@
+def work():
+    pass
+
+
+def error_types():
+    return (ValueError, KeyError)
+
+
+def recover():
+    pass
+
+
+def succeeded():
+    pass
+
+
+def run():
+    try:
+        work()
+    except error_types():
+        recover()
+    else:
+        succeeded()
@@ -0,0 +1,23 @@
+def check():
+    return True
+
+
+def on_true():
+    pass
+
+
+def on_elif():
+    pass
+
+
+def on_false():
+    pass
+
+
+def branches(x):
+    if check():
+        on_true()
+    elif x > 1:
+        on_elif()
+    else:
+        on_false()
@@ -0,0 +1,22 @@
+def command():
+    return "go"
+
+
+def is_ready():
+    return True
+
+
+def go():
+    pass
+
+
+def fallback():
+    pass
+
+
+def dispatch():
+    match command():
+        case "go" if is_ready():
+            go()
+        case _:
+            fallback()
@@ -0,0 +1,10 @@
+def make_error():
+    return ValueError("bad")
+
+
+def original_error():
+    return KeyError("missing")
+
+
+def fail():
+    raise make_error() from original_error()
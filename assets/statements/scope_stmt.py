@@ -0,0 +1,17 @@
+counter = 0
+
+
+def bump():
+    return 1
+
+
+def outer():
+    global counter
+    counter += bump()
+    total = 0
+
+    def inner():
+        nonlocal total
+        total += bump()
+
+    return inner
@@ -0,0 +1,19 @@
+def work():
+    pass
+
+
+def recover():
+    pass
+
+
+def cleanup():
+    pass
+
+
+def run():
+    try:
+        work()
+    except* ValueError:
+        recover()
+    finally:
+        cleanup()
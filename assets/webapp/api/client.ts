@@ -0,0 +1,32 @@
+import { formatQuery } from "../utils";
+
+export class Client {
+  constructor(private base: string) {}
+
+  async get(path: string): Promise<unknown> {
+    const response = await fetch(this.base + formatQuery(path));
+    if (!response.ok) {
+      throw new Error(`request failed: ${response.status}`);
+    }
+    return response.json();
+  }
+}
+
+export class TodoClient extends Client {
+  async get(path: string): Promise<unknown> {
+    try {
+      return await super.get(path);
+    } catch (error) {
+      console.error(error);
+      return null;
+    }
+  }
+
+  list() {
+    return this.get("/todos");
+  }
+}
+
+export default function createClient(base: string): TodoClient {
+  return new TodoClient(base);
+}
@@ -0,0 +1,20 @@
+import React, { useEffect, useState } from "react";
+import createClient from "../api/client";
+import * as utils from "../utils";
+
+type Props = { onSelect: (id: number) => void };
+
+export function TodoList({ onSelect }: Props) {
+  const [todos, setTodos] = useState([]);
+
+  useEffect(() => {
+    const client = createClient("/api");
+    client.list().then((items) => setTodos(items));
+  }, []);
+
+  const render = (todo) => (
+    <li onClick={() => onSelect(todo.id)}>{utils.joinPath(todo.title)}</li>
+  );
+
+  return <ul>{todos.map(render)}</ul>;
+}
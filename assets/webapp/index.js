@@ -0,0 +1,9 @@
+import { TodoList } from "./components/TodoList";
+import { render } from "react-dom";
+
+for (const id of ["root", "sidebar"]) {
+  const element = document.getElementById(id);
+  if (element) {
+    render(TodoList, element);
+  }
+}
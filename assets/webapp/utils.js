@@ -0,0 +1,9 @@
+const path = require("path");
+
+function formatQuery(query) {
+  return encodeURIComponent(query.trim());
+}
+
+const joinPath = (...parts) => path.join(...parts);
+
+module.exports = { formatQuery, joinPath };
@@ -0,0 +1,1377 @@
+use logos::Logos;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    call_graph::{
+        CallGraph, CallSite, Category, Context, Edge, EdgeKind, Location, NodeId, NodeKind,
+    },
+    error::GraphError,
+    frontend::{Frontend, GraphOptions},
+    imports,
+};
+
+/// Functions of the C standard library and POSIX. Sorted so they can be binary searched.
+const LIBC: &[&str] = &[
+    "abort",
+    "abs",
+    "assert",
+    "atexit",
+    "atof",
+    "atoi",
+    "atol",
+    "bsearch",
+    "calloc",
+    "ceil",
+    "clock",
+    "close",
+    "cos",
+    "exit",
+    "exp",
+    "fabs",
+    "fclose",
+    "feof",
+    "ferror",
+    "fflush",
+    "fgetc",
+    "fgets",
+    "floor",
+    "fmod",
+    "fopen",
+    "fprintf",
+    "fputc",
+    "fputs",
+    "fread",
+    "free",
+    "freopen",
+    "fscanf",
+    "fseek",
+    "ftell",
+    "fwrite",
+    "getc",
+    "getchar",
+    "getenv",
+    "gettimeofday",
+    "isalnum",
+    "isalpha",
+    "isdigit",
+    "islower",
+    "isspace",
+    "isupper",
+    "labs",
+    "log",
+    "log10",
+    "longjmp",
+    "lseek",
+    "malloc",
+    "memchr",
+    "memcmp",
+    "memcpy",
+    "memmove",
+    "memset",
+    "open",
+    "perror",
+    "pow",
+    "printf",
+    "putc",
+    "putchar",
+    "puts",
+    "qsort",
+    "raise",
+    "rand",
+    "read",
+    "realloc",
+    "remove",
+    "rename",
+    "scanf",
+    "setjmp",
+    "signal",
+    "sin",
+    "snprintf",
+    "sprintf",
+    "sqrt",
+    "srand",
+    "sscanf",
+    "strcat",
+    "strchr",
+    "strcmp",
+    "strcpy",
+    "strcspn",
+    "strdup",
+    "strerror",
+    "strlen",
+    "strncat",
+    "strncmp",
+    "strncpy",
+    "strndup",
+    "strrchr",
+    "strspn",
+    "strstr",
+    "strtod",
+    "strtok",
+    "strtol",
+    "strtoul",
+    "system",
+    "tan",
+    "time",
+    "tolower",
+    "toupper",
+    "va_arg",
+    "va_end",
+    "va_start",
+    "vfprintf",
+    "vprintf",
+    "vsnprintf",
+    "vsprintf",
+    "write",
+];
+
+/// Words that are followed by parentheses without being calls
+const KEYWORDS: &[&str] = &[
+    "_Alignof",
+    "_Generic",
+    "_Static_assert",
+    "__asm__",
+    "__attribute__",
+    "__declspec",
+    "__typeof__",
+    "alignof",
+    "asm",
+    "defined",
+    "do",
+    "else",
+    "for",
+    "if",
+    "return",
+    "sizeof",
+    "switch",
+    "typeof",
+    "while",
+];
+
+/// Words that start a statement that isn't a declaration
+const STATEMENTS: &[&str] = &[
+    "break", "case", "continue", "default", "do", "else", "for", "goto", "if", "return", "sizeof",
+    "switch", "while",
+];
+
+/// How deep calls inside macros are followed, e.g. for a macro that uses another one
+const MACRO_DEPTH: usize = 4;
+
+/// The C frontend. Like the JavaScript one, files are split into tokens rather than parsed,
+/// so macros and headers that can't be found don't get in the way. Preprocessor directives
+/// are left out, along with the `#else` branches of conditionals and `#if 0` blocks.
+///
+/// Functions are named by the path of the file they are defined in relative to the
+/// analyzed directory, and their own name, e.g. `src/vec.c:vec_push`. Calls through
+/// function pointers are [`EdgeKind::Indirect`]. Functions of Python extension modules,
+/// found through their `PyMethodDef` tables, get nodes named the way Python code calls them,
+/// e.g. `_speedups.fast_sum`, so calls from Python code lead into C.
+pub struct C;
+
+impl Frontend for C {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["c", "h"]
+    }
+
+    fn generate(
+        &self,
+        sources: Vec<(PathBuf, String)>,
+        base: Option<&Path>,
+        _options: &GraphOptions,
+    ) -> Result<CallGraph, GraphError> {
+        let sources = sources
+            .into_iter()
+            .map(|(path, src)| (path, preprocess(&src)))
+            .collect::<Vec<_>>();
+        let files = sources
+            .iter()
+            .map(|(path, source)| File::new(path, base, source))
+            .collect::<Vec<_>>();
+        Ok(Resolver::new(&files, &sources, base).build_graph())
+    }
+}
+
+/// A source file with its preprocessor directives blanked out. Lines are kept, so tokens
+/// are still on the line they were written on.
+struct Preprocessed {
+    src: String,
+    /// Headers included with quotes, as they are written
+    includes: Vec<String>,
+    /// Function-like macros, by name, with their bodies
+    macros: Vec<(String, String)>,
+}
+
+/// Blanks out preprocessor directives, and the lines that a conditional leaves out for
+/// certain: the branches after the first one, and `#if 0` blocks.
+fn preprocess(src: &str) -> Preprocessed {
+    let mut out = String::with_capacity(src.len());
+    let mut includes = Vec::new();
+    let mut macros = Vec::new();
+    // Whether each open conditional is in a branch that's kept, and whether one was kept
+    let mut levels: Vec<(bool, bool)> = Vec::new();
+    let mut lines = src.split('\n');
+    while let Some(line) = lines.next() {
+        let active = levels.iter().all(|&(active, _)| active);
+        let Some(directive) = line.trim_start().strip_prefix('#') else {
+            if active {
+                out.push_str(line);
+            }
+            out.push('\n');
+            continue;
+        };
+        out.push('\n');
+        // A directive goes on for as long as its lines end with a backslash
+        let mut directive = directive.trim_start().to_owned();
+        while directive.ends_with('\\') {
+            directive.pop();
+            let Some(next) = lines.next() else {
+                break;
+            };
+            directive.push(' ');
+            directive.push_str(next);
+            out.push('\n');
+        }
+        let (word, rest) = directive
+            .split_once(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or((&directive, ""));
+        match word {
+            "if" | "ifdef" | "ifndef" => {
+                let never = word == "if" && matches!(rest.trim(), "0" | "false");
+                levels.push((!never, !never));
+            }
+            "elif" | "else" => {
+                if let Some((active, taken)) = levels.last_mut() {
+                    *active = !*taken;
+                    *taken = true;
+                }
+            }
+            "endif" => {
+                levels.pop();
+            }
+            "include" if active => {
+                if let Some((header, _)) = rest
+                    .trim()
+                    .strip_prefix('"')
+                    .and_then(|rest| rest.split_once('"'))
+                {
+                    includes.push(header.to_owned());
+                }
+            }
+            "define" if active => {
+                let rest = rest.trim_start();
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                let (name, rest) = rest.split_at(end);
+                // Only macros that take arguments can stand in for calls
+                if let Some((_, body)) =
+                    rest.strip_prefix('(').and_then(|rest| rest.split_once(')'))
+                {
+                    macros.push((name.to_owned(), body.trim().to_owned()));
+                }
+            }
+            _ => {}
+        }
+    }
+    Preprocessed {
+        src: out,
+        includes,
+        macros,
+    }
+}
+
+#[derive(Logos, Debug, Clone, Copy, PartialEq, Eq)]
+#[logos(skip r"[ \t\r\n\f\\]+")]
+#[logos(skip r"//[^\n]*")]
+#[logos(skip r"/\*([^*]|\*+[^*/])*\*+/")]
+enum Token {
+    #[regex(r"[A-Za-z_][A-Za-z0-9_]*")]
+    Name,
+    #[regex(r"[0-9][0-9A-Za-z_.]*")]
+    Number,
+    #[regex(r#""([^"\\\n]|\\.)*""#)]
+    String,
+    #[regex(r"'([^'\\\n]|\\.)*'")]
+    Char,
+    #[token("(")]
+    OpenParen,
+    #[token(")")]
+    CloseParen,
+    #[token("{")]
+    OpenBrace,
+    #[token("}")]
+    CloseBrace,
+    #[token("[")]
+    OpenBracket,
+    #[token("]")]
+    CloseBracket,
+    #[token("=")]
+    Assign,
+    #[token(";")]
+    Semicolon,
+    #[token(",")]
+    Comma,
+    #[token(".")]
+    Dot,
+    #[token("->")]
+    Arrow,
+    #[token("*")]
+    Star,
+    #[token("&")]
+    Ampersand,
+    #[regex(r"[-+/%|^!~<>?:#]")]
+    #[token("...")]
+    #[token("==")]
+    #[token("!=")]
+    #[token("<=")]
+    #[token(">=")]
+    #[token("&&")]
+    #[token("||")]
+    #[token("++")]
+    #[token("--")]
+    #[token("<<")]
+    #[token(">>")]
+    #[token("+=")]
+    #[token("-=")]
+    #[token("*=")]
+    #[token("/=")]
+    #[token("%=")]
+    #[token("&=")]
+    #[token("|=")]
+    #[token("^=")]
+    #[token("<<=")]
+    #[token(">>=")]
+    Operator,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Lexeme<'s> {
+    token: Token,
+    text: &'s str,
+    /// 1-based
+    line: usize,
+    /// 1-based
+    column: usize,
+}
+
+/// Splits source code into tokens. Characters the lexer doesn't know are left out.
+fn tokenize(src: &str) -> Vec<Lexeme<'_>> {
+    let line_starts = std::iter::once(0)
+        .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+        .collect::<Vec<_>>();
+    let mut lexer = Token::lexer(src);
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.next() {
+        let Ok(token) = token else {
+            continue;
+        };
+        let start = lexer.span().start;
+        let line = line_starts.partition_point(|&line_start| line_start <= start);
+        tokens.push(Lexeme {
+            token,
+            text: lexer.slice(),
+            line,
+            column: start - line_starts[line - 1] + 1,
+        });
+    }
+    tokens
+}
+
+/// The index of the bracket matching each bracket.
+fn match_brackets(tokens: &[Lexeme]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; tokens.len()];
+    let mut open = Vec::new();
+    for (i, lexeme) in tokens.iter().enumerate() {
+        let opener = match lexeme.token {
+            Token::OpenParen | Token::OpenBrace | Token::OpenBracket => {
+                open.push(i);
+                continue;
+            }
+            Token::CloseParen => Token::OpenParen,
+            Token::CloseBrace => Token::OpenBrace,
+            Token::CloseBracket => Token::OpenBracket,
+            _ => continue,
+        };
+        // Unbalanced closing brackets are left unmatched
+        if let Some(&start) = open.last() {
+            if tokens[start].token == opener {
+                open.pop();
+                matches[start] = Some(i);
+                matches[i] = Some(start);
+            }
+        }
+    }
+    matches
+}
+
+/// The name in a `(*name)` declarator, e.g. of a function pointer.
+fn pointer_declarator<'s>(tokens: &[Lexeme<'s>]) -> Option<&'s str> {
+    tokens.windows(4).find_map(|window| match window {
+        [open, star, name, close]
+            if open.token == Token::OpenParen
+                && star.token == Token::Star
+                && name.token == Token::Name
+                && close.token == Token::CloseParen =>
+        {
+            Some(name.text)
+        }
+        _ => None,
+    })
+}
+
+fn category(name: &str) -> Category {
+    if name.starts_with("__builtin_") {
+        Category::Builtin
+    } else if LIBC.binary_search(&name).is_ok() {
+        Category::Stdlib
+    } else {
+        Category::ThirdParty
+    }
+}
+
+/// A function definition in a file.
+struct Function<'s> {
+    name: &'s str,
+    is_static: bool,
+    /// The index of its name
+    start: usize,
+    /// The indices of the braces around its body
+    body: (usize, usize),
+    /// Parameters and local variables. A call to one of these goes through a pointer.
+    variables: HashSet<&'s str>,
+}
+
+/// The functions, global variables and initializers of a file.
+struct File<'s> {
+    path: &'s Path,
+    /// The path relative to the analyzed directory, e.g. `src/vec.c`
+    name: String,
+    tokens: Vec<Lexeme<'s>>,
+    matches: Vec<Option<usize>>,
+    includes: &'s [String],
+    functions: Vec<Function<'s>>,
+    /// Variables declared outside of functions
+    variables: HashSet<&'s str>,
+    /// Brace initializers outside of functions, by where their declaration starts and their
+    /// opening brace
+    initializers: Vec<(usize, usize)>,
+}
+
+impl<'s> File<'s> {
+    fn new(path: &'s Path, base: Option<&Path>, source: &'s Preprocessed) -> Self {
+        let relative = base
+            .and_then(|base| path.strip_prefix(base).ok())
+            .unwrap_or_else(|| path.file_name().map_or(path, Path::new));
+        let name = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let tokens = tokenize(&source.src);
+        let matches = match_brackets(&tokens);
+        let mut file = Self {
+            path,
+            name,
+            tokens,
+            matches,
+            includes: &source.includes,
+            functions: Vec::new(),
+            variables: HashSet::new(),
+            initializers: Vec::new(),
+        };
+        file.outline();
+        file
+    }
+
+    fn token(&self, i: usize) -> Option<Token> {
+        self.tokens.get(i).map(|lexeme| lexeme.token)
+    }
+
+    /// Whether the token is a field name after `.` or `->`.
+    fn is_member(&self, i: usize) -> bool {
+        i > 0 && matches!(self.tokens[i - 1].token, Token::Dot | Token::Arrow)
+    }
+
+    fn location(&self, start_line: usize, end_line: usize) -> Location {
+        Location {
+            file: self.path.to_path_buf(),
+            start_line,
+            end_line,
+        }
+    }
+
+    /// Goes through the declarations outside of functions.
+    fn outline(&mut self) {
+        let n = self.tokens.len();
+        let mut start = 0;
+        let mut i = 0;
+        while i < n {
+            let lexeme = self.tokens[i];
+            match lexeme.token {
+                Token::Semicolon => {
+                    self.declaration(start, i);
+                    i += 1;
+                    start = i;
+                }
+                // The braces of `extern "C" {` only wrap declarations
+                Token::OpenBrace
+                    if i >= 2
+                        && self.tokens[i - 1].token == Token::String
+                        && self.tokens[i - 2].text == "extern" =>
+                {
+                    i += 1;
+                    start = i;
+                }
+                Token::OpenBrace => {
+                    // Struct bodies and initializers. The declaration goes on after them.
+                    if i > 0 && self.tokens[i - 1].token == Token::Assign {
+                        self.initializers.push((start, i));
+                    }
+                    i = self.matches[i].map_or(n, |close| close + 1);
+                }
+                Token::CloseBrace => {
+                    i += 1;
+                    start = i;
+                }
+                Token::Name
+                    if self.token(i + 1) == Some(Token::OpenParen)
+                        && KEYWORDS.binary_search(&lexeme.text).is_err() =>
+                {
+                    if let Some(open) = self.function_body(i) {
+                        let close = self.matches[open].unwrap_or(n - 1);
+                        let is_static = self.tokens[start..i]
+                            .iter()
+                            .any(|lexeme| lexeme.text == "static");
+                        let mut variables = self.parameters(i + 1);
+                        self.locals(open, close, &mut variables);
+                        self.functions.push(Function {
+                            name: lexeme.text,
+                            is_static,
+                            start: i,
+                            body: (open, close),
+                            variables,
+                        });
+                        i = close + 1;
+                        start = i;
+                    } else {
+                        i = self.matches[i + 1].map_or(i + 1, |close| close + 1);
+                    }
+                }
+                _ => i += 1,
+            }
+        }
+    }
+
+    /// The opening brace of a function's body, if the name at `i` starts a definition rather
+    /// than a declaration or a call of a macro.
+    fn function_body(&self, i: usize) -> Option<usize> {
+        let close = self.matches[i + 1]?;
+        let mut j = close + 1;
+        match self.token(j)? {
+            Token::OpenBrace => return Some(j),
+            Token::Name => {}
+            _ => return None,
+        }
+        // Attributes, and the parameter declarations of old-style definitions, e.g.
+        // `int main(argc, argv) int argc; char **argv; {`
+        while j < self.tokens.len() {
+            match self.tokens[j].token {
+                Token::OpenBrace => return Some(j),
+                Token::Name | Token::Star | Token::Comma | Token::Semicolon => j += 1,
+                Token::OpenBracket => j = self.matches[j]? + 1,
+                Token::OpenParen
+                    if matches!(
+                        self.tokens[j - 1].text,
+                        "__attribute__" | "__declspec" | "__asm__" | "asm"
+                    ) =>
+                {
+                    j = self.matches[j]? + 1;
+                }
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    /// The names of the parameters in the parentheses starting at `open`.
+    fn parameters(&self, open: usize) -> HashSet<&'s str> {
+        let Some(close) = self.matches[open] else {
+            return HashSet::new();
+        };
+        // Old-style parameter lists only have names
+        let old_style = self.token(close + 1) == Some(Token::Name);
+        let mut parameters = HashSet::new();
+        let mut segment_start = open + 1;
+        let mut j = open + 1;
+        while j <= close {
+            match self.tokens[j].token {
+                Token::OpenParen | Token::OpenBracket if j < close => {
+                    j = self.matches[j].map_or(close, |end| end + 1);
+                }
+                Token::Comma | Token::CloseParen => {
+                    let segment = &self.tokens[segment_start..j];
+                    let name = pointer_declarator(segment).or_else(|| {
+                        match segment {
+                            [name] if old_style => Some(name),
+                            // Arrays, e.g. `char *argv[]`
+                            [_, .., name, open, _] if open.token == Token::OpenBracket => {
+                                Some(name)
+                            }
+                            [_, .., name] => Some(name),
+                            _ => None,
+                        }
+                        .filter(|name| name.token == Token::Name)
+                        .map(|name| name.text)
+                    });
+                    parameters.extend(name);
+                    segment_start = j + 1;
+                    j += 1;
+                }
+                _ => j += 1,
+            }
+        }
+        parameters
+    }
+
+    /// Adds the variables declared in a function's body.
+    fn locals(&self, open: usize, close: usize, variables: &mut HashSet<&'s str>) {
+        for j in open + 1..close {
+            if !matches!(
+                self.tokens[j - 1].token,
+                Token::OpenBrace | Token::CloseBrace | Token::Semicolon
+            ) {
+                continue;
+            }
+            // Type words, then the declared name, e.g. `struct ops *ops = ...`
+            let mut k = j;
+            let mut words = 0;
+            while k < close {
+                match self.tokens[k].token {
+                    Token::Name if !STATEMENTS.contains(&self.tokens[k].text) => words += 1,
+                    Token::Star => {}
+                    _ => break,
+                }
+                k += 1;
+            }
+            if words >= 1 && self.tokens[k].token == Token::OpenParen {
+                variables.extend(pointer_declarator(&self.tokens[k..close.min(k + 4)]));
+                continue;
+            }
+            if words < 2
+                || self.tokens[k - 1].token != Token::Name
+                || !matches!(
+                    self.tokens[k].token,
+                    Token::Assign | Token::Semicolon | Token::Comma | Token::OpenBracket
+                )
+            {
+                continue;
+            }
+            variables.insert(self.tokens[k - 1].text);
+            // More declarators after commas, e.g. `int a = 1, *b;`
+            while k < close {
+                match self.tokens[k].token {
+                    Token::Semicolon => break,
+                    Token::OpenParen | Token::OpenBrace | Token::OpenBracket => {
+                        k = self.matches[k].unwrap_or(close);
+                    }
+                    Token::Comma => {
+                        let mut name = k + 1;
+                        while self.token(name) == Some(Token::Star) {
+                            name += 1;
+                        }
+                        if self.token(name) == Some(Token::Name) {
+                            variables.insert(self.tokens[name].text);
+                        }
+                    }
+                    _ => {}
+                }
+                k += 1;
+            }
+        }
+    }
+
+    /// Adds the variable declared by the tokens from `start` up to the semicolon at `end`.
+    fn declaration(&mut self, start: usize, end: usize) {
+        let tokens = &self.tokens[start..end];
+        if tokens.len() < 2 || tokens.iter().any(|lexeme| lexeme.text == "typedef") {
+            return;
+        }
+        if let Some(name) = pointer_declarator(tokens) {
+            self.variables.insert(name);
+            return;
+        }
+        let mut k = tokens
+            .iter()
+            .position(|lexeme| lexeme.token == Token::Assign)
+            .map_or(end, |i| start + i);
+        while k > start && self.tokens[k - 1].token == Token::CloseBracket {
+            match self.matches[k - 1] {
+                Some(open) => k = open,
+                None => return,
+            }
+        }
+        if k >= start + 2
+            && self.tokens[k - 1].token == Token::Name
+            && !matches!(self.tokens[k - 2].text, "struct" | "union" | "enum")
+        {
+            self.variables.insert(self.tokens[k - 1].text);
+        }
+    }
+
+    /// The bracketed group around a token, by its opening and closing bracket.
+    fn enclosing_group(&self, i: usize) -> Option<(usize, usize)> {
+        let mut depth = 0;
+        for k in (0..i).rev() {
+            match self.tokens[k].token {
+                Token::CloseParen | Token::CloseBrace | Token::CloseBracket => depth += 1,
+                Token::OpenParen | Token::OpenBrace | Token::OpenBracket if depth == 0 => {
+                    return Some((k, self.matches[k]?));
+                }
+                Token::OpenParen | Token::OpenBrace | Token::OpenBracket => depth -= 1,
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// The pointer a call goes through, as written, e.g. `self->ops` for
+    /// `self->ops->read()`. `i` is the `.` or `->` in front of the field.
+    fn receiver(&self, mut i: usize) -> Option<String> {
+        let mut receiver = String::new();
+        while i > 0 {
+            let end = i - 1;
+            let (text, start) = match self.tokens[end].token {
+                Token::Name => (self.tokens[end].text.to_owned(), end),
+                // Calls and indexing, e.g. `get_ops()->read` or `handlers[i].run`
+                Token::CloseParen | Token::CloseBracket => match self.matches[end] {
+                    Some(open) if open > 0 && self.tokens[open - 1].token == Token::Name => {
+                        let brackets = if self.tokens[end].token == Token::CloseParen {
+                            "()"
+                        } else {
+                            "[]"
+                        };
+                        (
+                            format!("{}{brackets}", self.tokens[open - 1].text),
+                            open - 1,
+                        )
+                    }
+                    _ => break,
+                },
+                _ => break,
+            };
+            receiver = if receiver.is_empty() {
+                text
+            } else {
+                format!("{text}{}{receiver}", self.tokens[i].text)
+            };
+            if !self.is_member(start) {
+                break;
+            }
+            i = start - 1;
+        }
+        (!receiver.is_empty()).then_some(receiver)
+    }
+}
+
+/// A function of an extension module, as Python code calls it.
+struct Binding {
+    /// The dotted name in Python, e.g. `_speedups.fast_sum`
+    name: String,
+    /// The C function behind it
+    function: String,
+    /// Where its entry in the method table is
+    file: usize,
+    line: usize,
+    column: usize,
+}
+
+/// An entry of a `PyMethodDef` table: the name in Python, the C function, and where it is.
+struct MethodEntry<'s> {
+    name: &'s str,
+    function: String,
+    line: usize,
+    column: usize,
+}
+
+/// What a call goes to.
+enum Target {
+    /// A function, or a function that's only known by name
+    Direct(Vec<(String, Category)>),
+    /// A function pointer, by the functions that may be stored in it, or the pointer as
+    /// written if there are none
+    Indirect(Vec<String>, String),
+}
+
+/// Links up calls across files.
+struct Resolver<'a, 's> {
+    files: &'a [File<'s>],
+    base: Option<&'a Path>,
+    /// Files a function is defined in, and whether it is static there
+    functions: HashMap<&'s str, Vec<(usize, bool)>>,
+    macros: HashMap<&'s str, &'s str>,
+    /// Functions stored in a struct field, by the field's name
+    fields: HashMap<&'s str, Vec<String>>,
+    /// Functions stored in a variable, or in an array, by its name
+    pointers: HashMap<&'s str, Vec<String>>,
+    bindings: Vec<Binding>,
+}
+
+impl<'a, 's> Resolver<'a, 's> {
+    fn new(
+        files: &'a [File<'s>],
+        sources: &'s [(PathBuf, Preprocessed)],
+        base: Option<&'a Path>,
+    ) -> Self {
+        let mut functions = HashMap::<_, Vec<_>>::new();
+        for (i, file) in files.iter().enumerate() {
+            for function in &file.functions {
+                functions
+                    .entry(function.name)
+                    .or_default()
+                    .push((i, function.is_static));
+            }
+        }
+        let macros = sources
+            .iter()
+            .flat_map(|(_, source)| &source.macros)
+            .map(|(name, body)| (name.as_str(), body.as_str()))
+            .collect();
+        let mut resolver = Self {
+            files,
+            base,
+            functions,
+            macros,
+            fields: HashMap::new(),
+            pointers: HashMap::new(),
+            bindings: Vec::new(),
+        };
+        for i in 0..files.len() {
+            resolver.collect(i);
+        }
+        resolver
+    }
+
+    fn qualified(&self, file: usize, name: &str) -> String {
+        format!("{}:{name}", self.files[file].name)
+    }
+
+    /// The function a name in a file refers to: one defined in the file, then one defined in
+    /// a header it includes, then one that isn't static anywhere else.
+    fn resolve(&self, file: usize, name: &str) -> Option<String> {
+        let definitions = self.functions.get(name)?;
+        let included = |i: usize| {
+            self.files[file]
+                .includes
+                .iter()
+                .any(|header| self.files[i].path.ends_with(header))
+        };
+        let &(i, _) = definitions
+            .iter()
+            .find(|&&(i, _)| i == file)
+            .or_else(|| definitions.iter().find(|&&(i, _)| included(i)))
+            .or_else(|| definitions.iter().find(|&&(_, is_static)| !is_static))?;
+        Some(self.qualified(i, name))
+    }
+
+    /// Finds where a file stores functions, and its extension module method tables.
+    fn collect(&mut self, i: usize) {
+        let files = self.files;
+        let file = &files[i];
+        for &(start, open) in &file.initializers {
+            let close = file.matches[open].unwrap_or(file.tokens.len());
+            let declared = file.tokens[start..open]
+                .iter()
+                .take_while(|lexeme| !matches!(lexeme.token, Token::OpenBracket | Token::Assign))
+                .filter(|lexeme| lexeme.token == Token::Name)
+                .last()
+                .map(|lexeme| lexeme.text);
+            self.collect_stores(i, open, close, declared);
+        }
+        for function in &file.functions {
+            self.collect_stores(i, function.body.0, function.body.1, None);
+        }
+
+        let mut tables = HashMap::new();
+        for &(start, open) in &file.initializers {
+            if !file.tokens[start..open]
+                .iter()
+                .any(|lexeme| lexeme.text == "PyMethodDef")
+            {
+                continue;
+            }
+            let Some(name) = file.tokens[start..open]
+                .iter()
+                .take_while(|lexeme| !matches!(lexeme.token, Token::OpenBracket | Token::Assign))
+                .filter(|lexeme| lexeme.token == Token::Name)
+                .last()
+            else {
+                continue;
+            };
+            tables.insert(name.text, self.method_table(i, open));
+        }
+        // A table is bound to a module or type by the initializer or call it's used in,
+        // whose first string is the module's or type's name, e.g. in
+        // `{PyModuleDef_HEAD_INIT, "_speedups", NULL, -1, methods}`
+        for (j, lexeme) in file.tokens.iter().enumerate() {
+            let Some(entries) = tables.get(lexeme.text) else {
+                continue;
+            };
+            if lexeme.token != Token::Name
+                || matches!(file.token(j + 1), Some(Token::OpenBracket | Token::Assign))
+            {
+                continue;
+            }
+            let Some(owner) = file.enclosing_group(j).and_then(|(open, close)| {
+                file.tokens[open + 1..close]
+                    .iter()
+                    .find(|lexeme| lexeme.token == Token::String)
+            }) else {
+                continue;
+            };
+            let owner = self.python_name(i, owner.text.trim_matches('"'));
+            for entry in entries {
+                self.bindings.push(Binding {
+                    name: format!("{owner}.{}", entry.name),
+                    function: entry.function.clone(),
+                    file: i,
+                    line: entry.line,
+                    column: entry.column,
+                });
+            }
+        }
+    }
+
+    /// Records the functions stored by `x = f`, `x->field = f` and `.field = f` between two
+    /// brackets. Functions listed in the initializer of `declared` are stored in it.
+    fn collect_stores(&mut self, i: usize, open: usize, close: usize, declared: Option<&'s str>) {
+        let files = self.files;
+        let file = &files[i];
+        for j in open + 1..close {
+            let lexeme = file.tokens[j];
+            if lexeme.token != Token::Name
+                || file.token(j + 1) == Some(Token::OpenParen)
+                || file.is_member(j)
+            {
+                continue;
+            }
+            let Some(function) = self.resolve(i, lexeme.text) else {
+                continue;
+            };
+            let mut k = j - 1;
+            if file.tokens[k].token == Token::Ampersand {
+                k -= 1;
+            }
+            if file.tokens[k].token == Token::Assign && file.tokens[k - 1].token == Token::Name {
+                let target = file.tokens[k - 1].text;
+                let stores = if file.is_member(k - 1) {
+                    &mut self.fields
+                } else {
+                    &mut self.pointers
+                };
+                stores.entry(target).or_default().push(function);
+            } else if let Some(declared) = declared {
+                self.pointers.entry(declared).or_default().push(function);
+            }
+        }
+    }
+
+    /// The entries of a `PyMethodDef` table, e.g. `{"fast_sum", fast_sum, METH_VARARGS, ""}`.
+    /// Functions that aren't defined in the analyzed files are left out.
+    fn method_table(&self, i: usize, open: usize) -> Vec<MethodEntry<'s>> {
+        let file = &self.files[i];
+        let close = file.matches[open].unwrap_or(file.tokens.len());
+        let mut entries = Vec::new();
+        let mut j = open + 1;
+        while j < close {
+            let entry_close = match file.matches[j] {
+                Some(entry_close) if file.tokens[j].token == Token::OpenBrace => entry_close,
+                _ => {
+                    j += 1;
+                    continue;
+                }
+            };
+            // The sentinel that ends the table may be empty, i.e. `{}`
+            if entry_close <= j + 2 {
+                j = entry_close + 1;
+                continue;
+            }
+            let name = file.tokens[j + 1];
+            // The function comes after the name, often behind a cast
+            let function = file.tokens[j + 2..entry_close]
+                .iter()
+                .filter(|lexeme| lexeme.token == Token::Name)
+                .find_map(|lexeme| self.resolve(i, lexeme.text));
+            if let (Token::String, Some(function)) = (name.token, function) {
+                entries.push(MethodEntry {
+                    name: name.text.trim_matches('"'),
+                    function,
+                    line: name.line,
+                    column: name.column,
+                });
+            }
+            j = entry_close + 1;
+        }
+        entries
+    }
+
+    /// The dotted name Python code uses for a module or type of an extension module. Names
+    /// that aren't already qualified are taken to be in the package of the C file.
+    fn python_name(&self, file: usize, owner: &str) -> String {
+        let path = self.files[file].path;
+        let head = owner.split('.').next().unwrap_or(owner);
+        let module = path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(format!("{head}.py"));
+        let (module, _) = imports::qualified_module_name(&module, self.base);
+        let package = module.strip_suffix(head).unwrap_or_default();
+        if owner.starts_with(package) {
+            owner.to_owned()
+        } else {
+            format!("{package}{owner}")
+        }
+    }
+
+    fn build_graph(&self) -> CallGraph {
+        let mut graph = CallGraph::default();
+        for (i, file) in self.files.iter().enumerate() {
+            for function in &file.functions {
+                let start_line = file.tokens[function.start].line;
+                let end_line = file.tokens[function.body.1].line;
+                graph.define(
+                    &self.qualified(i, function.name),
+                    NodeKind::Function,
+                    file.location(start_line, end_line),
+                    None,
+                );
+            }
+        }
+
+        for binding in &self.bindings {
+            let location = self.files[binding.file].location(binding.line, binding.line);
+            let caller = graph.define(&binding.name, NodeKind::Function, location, None);
+            let callee = graph
+                .find(&binding.function)
+                .expect("bound functions are defined above");
+            graph.edges.push(Edge {
+                caller,
+                callee,
+                kind: EdgeKind::Implicit,
+                sites: vec![CallSite {
+                    receiver: None,
+                    line: binding.line,
+                    column: binding.column,
+                    context: Context::default(),
+                }],
+                count: None,
+            });
+        }
+
+        for (i, file) in self.files.iter().enumerate() {
+            for function in &file.functions {
+                self.walk(&mut graph, i, function);
+            }
+        }
+        graph.merge_edges();
+        graph
+    }
+
+    /// Adds the calls and references a function's body makes.
+    fn walk(&self, graph: &mut CallGraph, i: usize, function: &Function) {
+        let file = &self.files[i];
+        let caller = graph
+            .find(&self.qualified(i, function.name))
+            .expect("functions are defined before their bodies are walked");
+        let (open, close) = function.body;
+        // Control flow bodies, by the token they end at, or the depth of parentheses of a
+        // body without braces, which ends at a semicolon
+        let mut frames: Vec<(Result<usize, usize>, Context)> = Vec::new();
+        let mut pending: Vec<(usize, Context)> = Vec::new();
+        let mut depth = 0;
+        for j in open + 1..close {
+            let lexeme = file.tokens[j];
+            let mut ended = false;
+            while let Some(&(Ok(end), _)) = frames.last() {
+                if end > j {
+                    break;
+                }
+                frames.pop();
+                ended = true;
+            }
+            // A braced body ends the body without braces it's the statement of, unless an
+            // `else` or the `while` of a `do` follows
+            if ended && !matches!(lexeme.text, "else" | "while") {
+                while matches!(frames.last(), Some(&(Err(d), _)) if d == depth) {
+                    frames.pop();
+                }
+            }
+            while pending.last().is_some_and(|&(start, _)| start < j) {
+                pending.pop();
+            }
+            if let Some(&(start, context)) = pending.last() {
+                if start == j {
+                    pending.pop();
+                    let end = match (lexeme.token, file.matches[j]) {
+                        (Token::OpenBrace, Some(end)) => Ok(end),
+                        _ => Err(depth),
+                    };
+                    frames.push((end, context));
+                }
+            }
+            let context = frames.last().map_or_else(Context::default, |frame| frame.1);
+            let site = |receiver: Option<String>, at: Lexeme| CallSite {
+                receiver,
+                line: at.line,
+                column: at.column,
+                context,
+            };
+
+            match lexeme.token {
+                Token::OpenParen => {
+                    depth += 1;
+                    // `(*pointer)(...)`
+                    let through = file.tokens[j + 1..close.min(j + 5)]
+                        .iter()
+                        .map(|lexeme| lexeme.token)
+                        .collect::<Vec<_>>();
+                    if through
+                        == [
+                            Token::Star,
+                            Token::Name,
+                            Token::CloseParen,
+                            Token::OpenParen,
+                        ]
+                    {
+                        let pointer = file.tokens[j + 2];
+                        let target =
+                            self.indirect(&self.pointers, pointer.text, pointer.text.to_owned());
+                        self.add_call(graph, caller, target, site(None, pointer));
+                    }
+                }
+                Token::CloseParen => depth = depth.saturating_sub(1),
+                Token::Semicolon => {
+                    while matches!(frames.last(), Some(&(Err(d), _)) if d == depth) {
+                        frames.pop();
+                    }
+                }
+                // `handlers[i](...)`
+                Token::CloseBracket if file.token(j + 1) == Some(Token::OpenParen) => {
+                    if let Some(open) = file.matches[j].filter(|&open| open > 0) {
+                        let array = file.tokens[open - 1];
+                        if array.token == Token::Name && !file.is_member(open - 1) {
+                            let target = self.indirect(
+                                &self.pointers,
+                                array.text,
+                                format!("{}[]", array.text),
+                            );
+                            self.add_call(graph, caller, target, site(None, array));
+                        }
+                    }
+                }
+                Token::Name if file.token(j + 1) == Some(Token::OpenParen) => {
+                    let name = lexeme.text;
+                    let head_close = file.matches[j + 1].unwrap_or(close);
+                    let nested = |in_loop: bool, conditional: bool| Context {
+                        in_loop: context.in_loop || in_loop,
+                        conditional: context.conditional || conditional,
+                        ..context
+                    };
+                    if file.is_member(j) {
+                        let receiver = file.receiver(j - 1);
+                        let written = match &receiver {
+                            Some(receiver) => {
+                                format!("{receiver}{}{name}", file.tokens[j - 1].text)
+                            }
+                            None => name.to_owned(),
+                        };
+                        let target = self.indirect(&self.fields, name, written);
+                        self.add_call(graph, caller, target, site(receiver, lexeme));
+                        continue;
+                    }
+                    match name {
+                        "if" | "switch" => pending.push((head_close + 1, nested(false, true))),
+                        "for" => pending.push((head_close + 1, nested(true, true))),
+                        "while" => {
+                            frames.push((Ok(head_close), nested(true, false)));
+                            pending.push((head_close + 1, nested(true, true)));
+                        }
+                        _ if KEYWORDS.binary_search(&name).is_ok() => {}
+                        _ if function.variables.contains(name)
+                            || (file.variables.contains(name)
+                                && self.resolve(i, name).is_none()) =>
+                        {
+                            let target = self.indirect(&self.pointers, name, name.to_owned());
+                            self.add_call(graph, caller, target, site(None, lexeme));
+                        }
+                        _ => {
+                            let target = Target::Direct(self.direct(i, name, 0));
+                            self.add_call(graph, caller, target, site(None, lexeme));
+                        }
+                    }
+                }
+                Token::Name if lexeme.text == "else" && file.tokens[j + 1].text != "if" => {
+                    pending.push((
+                        j + 1,
+                        Context {
+                            conditional: true,
+                            ..context
+                        },
+                    ));
+                }
+                Token::Name if lexeme.text == "do" => {
+                    pending.push((
+                        j + 1,
+                        Context {
+                            in_loop: true,
+                            ..context
+                        },
+                    ));
+                }
+                // A function that's passed around or stored
+                Token::Name if !file.is_member(j) && !function.variables.contains(lexeme.text) => {
+                    if let Some(callee) = self.resolve(i, lexeme.text) {
+                        let callee = graph.find(&callee).expect("functions are defined above");
+                        graph.edges.push(Edge {
+                            caller,
+                            callee,
+                            kind: EdgeKind::Reference,
+                            sites: vec![site(None, lexeme)],
+                            count: None,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// What a call through a name goes to. Calls of macros go to the calls in their body.
+    fn direct(&self, i: usize, name: &str, depth: usize) -> Vec<(String, Category)> {
+        if let Some(function) = self.resolve(i, name) {
+            return vec![(function, Category::Project)];
+        }
+        let Some(body) = self.macros.get(name).filter(|_| depth < MACRO_DEPTH) else {
+            return vec![(name.to_owned(), category(name))];
+        };
+        let tokens = tokenize(body);
+        tokens
+            .windows(2)
+            .filter(|pair| {
+                pair[0].token == Token::Name
+                    && pair[1].token == Token::OpenParen
+                    && KEYWORDS.binary_search(&pair[0].text).is_err()
+            })
+            .flat_map(|pair| self.direct(i, pair[0].text, depth + 1))
+            .collect()
+    }
+
+    fn indirect(
+        &self,
+        stores: &HashMap<&'s str, Vec<String>>,
+        key: &str,
+        written: String,
+    ) -> Target {
+        let mut candidates = stores.get(key).cloned().unwrap_or_default();
+        candidates.sort();
+        candidates.dedup();
+        Target::Indirect(candidates, written)
+    }
+
+    fn add_call(&self, graph: &mut CallGraph, caller: NodeId, target: Target, site: CallSite) {
+        let (kind, callees) = match target {
+            Target::Direct(callees) => (EdgeKind::Call, callees),
+            // A pointer nothing is stored in only means something inside of its caller
+            Target::Indirect(candidates, written) if candidates.is_empty() => {
+                let placeholder = format!("{}.<{written}>", graph.node(caller).name);
+                (EdgeKind::Indirect, vec![(placeholder, Category::Project)])
+            }
+            Target::Indirect(candidates, _) => (
+                EdgeKind::Indirect,
+                candidates
+                    .into_iter()
+                    .map(|candidate| (candidate, Category::Project))
+                    .collect(),
+            ),
+        };
+        for (callee, category) in callees {
+            // Until a definition turns up, callees are only known by where they come from
+            let node_kind = match category {
+                Category::Builtin => NodeKind::Builtin,
+                Category::Stdlib | Category::ThirdParty => NodeKind::External,
+                Category::Project => NodeKind::Function,
+            };
+            let callee = graph.add_node(&callee, node_kind, category);
+            graph.edges.push(Edge {
+                caller,
+                callee,
+                kind,
+                sites: vec![site.clone()],
+                count: None,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::generate_graph_from_path;
+
+    fn edge<'g>(graph: &'g CallGraph, caller: &str, callee: &str) -> &'g Edge {
+        graph
+            .edge(caller, callee)
+            .unwrap_or_else(|| panic!("no edge from {caller} to {callee}"))
+    }
+
+    #[test]
+    fn vec() {
+        let graph =
+            generate_graph_from_path(Path::new("assets/native/vec.c"), &GraphOptions::default())
+                .unwrap();
+
+        edge(&graph, "vec.c:vec_push", "vec.c:grow");
+        edge(&graph, "vec.c:vec_mean", "vec.c:vec_sum");
+        let free = edge(&graph, "vec.c:vec_free", "free");
+        assert_eq!(graph.node(free.callee).category, Category::Stdlib);
+        let abort = edge(&graph, "vec.c:vec_check", "abort");
+        assert!(abort.sites[0].context.conditional);
+
+        // Nothing is known to be stored in `fn`, so it's only known to its caller
+        let apply = edge(&graph, "vec.c:vec_apply", "vec.c:vec_apply.<fn>");
+        assert_eq!(apply.kind, EdgeKind::Indirect);
+        assert!(apply.sites[0].context.in_loop);
+        assert!(graph.find("fn").is_none());
+    }
+
+    #[test]
+    fn extension_module() {
+        let graph =
+            generate_graph_from_path(Path::new("assets/native"), &GraphOptions::default()).unwrap();
+
+        // Python calls the functions of the extension through its method table
+        edge(&graph, "stats.total", "_speedups.fast_sum");
+        let binding = edge(&graph, "_speedups.fast_sum", "_speedups.c:fast_sum");
+        assert_eq!(binding.kind, EdgeKind::Implicit);
+        edge(&graph, "_speedups.fast_reduce", "_speedups.c:fast_reduce");
+        // The table ends with an empty `{}` entry
+        let bindings = graph
+            .nodes()
+            .filter(|(_, node)| node.name.starts_with("_speedups.") && !node.name.contains(':'));
+        assert_eq!(bindings.count(), 2);
+
+        // Function pointers go to every function that is stored in them
+        for reducer in ["_speedups.c:reduce_sum", "_speedups.c:reduce_max"] {
+            let call = edge(&graph, "_speedups.c:reduce_with", reducer);
+            assert_eq!(call.kind, EdgeKind::Indirect);
+        }
+        edge(&graph, "_speedups.c:reduce_sum", "vec.c:vec_sum");
+        // Through the `VEC_CHECK` macro of `vec.h`
+        edge(&graph, "vec.c:vec_sum", "vec.c:vec_check");
+        edge(&graph, "_speedups.c:to_vec", "vec.c:vec_push");
+        let api = edge(&graph, "_speedups.c:fast_sum", "PyArg_ParseTuple");
+        assert_eq!(graph.node(api.callee).category, Category::ThirdParty);
+    }
+}
@@ -0,0 +1,433 @@
+use anyhow::{bail, Result};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use crate::error::GraphError;
+
+/// Functions and the calls between them, shared by the graph generator and the viewer. Nodes
+/// are keyed by qualified name, e.g. `pkg.module.Class.method` or
+/// `pkg.module.outer.<locals>.inner`.
+#[derive(Debug, Default, Clone)]
+pub struct CallGraph {
+    nodes: Vec<Node>,
+    ids: HashMap<String, NodeId>,
+    /// Every edge, in the order they were found. Call sites with the same caller, callee and
+    /// kind share an edge once [`CallGraph::merge_edges`] ran
+    pub edges: Vec<Edge>,
+    /// Calls that weren't resolved because their receiver could have more than one type
+    pub ambiguities: Vec<Ambiguity>,
+    /// Files that were left out of the graph, when it was built leniently
+    pub errors: Vec<GraphError>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    /// Qualified name
+    pub name: String,
+    pub kind: NodeKind,
+    pub category: Category,
+    /// Where the node is defined, if it is part of the analyzed code
+    pub location: Option<Location>,
+    /// The function or class this node is defined in. Top-level definitions have none
+    pub parent: Option<NodeId>,
+    /// How many calls this node makes to callees that were hidden with [`CallGraph::hide`],
+    /// so they still count toward metrics. Edges that aren't calls, see [`EdgeKind::is_call`],
+    /// aren't counted
+    pub hidden_calls: usize,
+    /// Seconds spent in the node and everything it called, for graphs loaded from a profile
+    pub cumulative_time: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    Function,
+    /// A function defined directly in a class body
+    Method,
+    Class,
+    Lambda,
+    /// Code at the top level of a module, named like `<module pkg.utils>`
+    Module,
+    /// A builtin function or type, or a method of a builtin type
+    Builtin,
+    /// Something outside of the analyzed code, e.g. in the standard library
+    External,
+}
+
+/// Where a function comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    /// Python's builtin functions and types, and methods of builtin types
+    Builtin,
+    Stdlib,
+    /// Imported from outside of the analyzed code and the standard library
+    ThirdParty,
+    /// Defined in the analyzed code, or unknown
+    Project,
+}
+
+impl Category {
+    /// Whether the code lives outside of the analyzed code.
+    pub fn is_external(self) -> bool {
+        matches!(self, Self::Stdlib | Self::ThirdParty)
+    }
+}
+
+impl FromStr for Category {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "builtin" => Ok(Self::Builtin),
+            "stdlib" => Ok(Self::Stdlib),
+            "third-party" => Ok(Self::ThirdParty),
+            "project" => Ok(Self::Project),
+            _ => bail!("unknown category `{s}`, expected builtin, stdlib, third-party or project"),
+        }
+    }
+}
+
+/// Where a function is defined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: PathBuf,
+    /// 1-based, inclusive
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+    pub caller: NodeId,
+    pub callee: NodeId,
+    pub kind: EdgeKind,
+    /// Every place the call is made, in source order
+    pub sites: Vec<CallSite>,
+    /// How many times the call was made, for graphs loaded from a profile
+    pub count: Option<usize>,
+}
+
+impl Edge {
+    /// How many times the caller makes this call: the count from a profile, or else the
+    /// number of call sites.
+    pub fn weight(&self) -> usize {
+        self.count.unwrap_or(self.sites.len())
+    }
+
+    /// Whether one of the call sites runs every time the caller does. Calls from a profile
+    /// have no sites, and count as unconditional since they were made.
+    pub fn is_unconditional(&self) -> bool {
+        self.sites.is_empty() && self.count.is_some()
+            || self
+                .sites
+                .iter()
+                .any(|site| site.context.is_unconditional())
+    }
+
+    /// Whether every call site is in an `except` block.
+    pub fn is_error_only(&self) -> bool {
+        !self.sites.is_empty() && self.sites.iter().all(|site| site.context.in_except)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// A call through a plain name, e.g. `load()`
+    Call,
+    /// A call through an attribute, e.g. `self.load()` or `os.path.join()`
+    AttributeCall,
+    /// Applying a decorator to a function or class
+    Decorator,
+    /// A function that is passed around without being called, e.g. as a callback
+    Reference,
+    /// Calling a class to create an instance
+    Instantiation,
+    /// A special method that Python calls for some syntax, e.g. `__enter__` for a `with` block,
+    /// or the C function behind a function of an extension module
+    Implicit,
+    /// A method that may run instead of the caller, because a subclass overrides it. Its site
+    /// is where the override is defined
+    Dispatch,
+    /// A call through a function pointer in C, e.g. `ops->read(buf)`. It goes to every
+    /// function that is stored where the pointer is read from, or to the pointer as written
+    /// if none is known
+    Indirect,
+}
+
+impl EdgeKind {
+    /// Whether the caller itself makes the call. References, dispatch to overrides and
+    /// calls the language makes for some syntax aren't.
+    pub fn is_call(self) -> bool {
+        matches!(
+            self,
+            Self::Call
+                | Self::AttributeCall
+                | Self::Decorator
+                | Self::Instantiation
+                | Self::Indirect
+        )
+    }
+}
+
+/// Where a call is made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallSite {
+    /// The dotted receiver of an attribute call as written, e.g. `os.path` or `self.db`
+    pub receiver: Option<String>,
+    /// 1-based line, in the caller's file
+    pub line: usize,
+    /// 1-based column
+    pub column: usize,
+    pub context: Context,
+}
+
+/// The control flow a call is made in. Calls in nested blocks have the flags of every block
+/// around them, up to the function they are made in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Context {
+    /// In a loop, so the call may be made many times
+    pub in_loop: bool,
+    /// In a branch of an `if` or `match`, the body of a loop, or an `else` block, so the call
+    /// may not be made at all
+    pub conditional: bool,
+    /// In an `except` block, so the call is only made on error
+    pub in_except: bool,
+    /// In a `finally` block
+    pub in_finally: bool,
+    /// In the body of a `with` block
+    pub in_with: bool,
+}
+
+impl Context {
+    /// Whether the call is made every time the caller runs, unless it returns or raises
+    /// before.
+    pub fn is_unconditional(self) -> bool {
+        !self.conditional && !self.in_except
+    }
+}
+
+/// A call through a variable that is assigned instances of different classes, e.g. `x.parse()`
+/// after both `x = JsonParser()` and `x = XmlParser()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ambiguity {
+    pub caller: NodeId,
+    /// The callee the edge was added with, e.g. `x.parse`
+    pub call: String,
+    /// Qualified names of the methods it could go to
+    pub candidates: Vec<String>,
+    pub site: CallSite,
+}
+
+impl CallGraph {
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        &mut self.nodes[id.0]
+    }
+
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.ids.get(name).copied()
+    }
+
+    /// The edge between two nodes, by name.
+    #[cfg(test)]
+    pub fn edge(&self, caller: &str, callee: &str) -> Option<&Edge> {
+        let (caller, callee) = (self.find(caller)?, self.find(callee)?);
+        self.edges
+            .iter()
+            .find(|edge| edge.caller == caller && edge.callee == callee)
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &Node)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (NodeId(i), node))
+    }
+
+    /// The calls made by a node.
+    pub fn calls_from(&self, id: NodeId) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |edge| edge.caller == id)
+    }
+
+    /// The functions and classes defined directly in a node.
+    pub fn children(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes()
+            .filter(move |(_, node)| node.parent == Some(id))
+            .map(|(child, _)| child)
+    }
+
+    /// How many calls a node makes, including calls to hidden callees.
+    pub fn fan_out(&self, id: NodeId) -> usize {
+        let calls = self
+            .calls_from(id)
+            .filter(|edge| edge.kind.is_call())
+            .map(Edge::weight);
+        calls.sum::<usize>() + self.node(id).hidden_calls
+    }
+
+    /// Adds a node that is only known by name, e.g. a callee. Nodes that already exist are
+    /// left as they are.
+    pub fn add_node(&mut self, name: &str, kind: NodeKind, category: Category) -> NodeId {
+        if let Some(id) = self.find(name) {
+            return id;
+        }
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Node {
+            name: name.to_owned(),
+            kind,
+            category,
+            location: None,
+            parent: None,
+            hidden_calls: 0,
+            cumulative_time: None,
+        });
+        self.ids.insert(name.to_owned(), id);
+        id
+    }
+
+    /// Adds a node for a definition. A node that was already added as a callee takes on
+    /// the definition's details.
+    pub fn define(
+        &mut self,
+        name: &str,
+        kind: NodeKind,
+        location: Location,
+        parent: Option<NodeId>,
+    ) -> NodeId {
+        let id = self.add_node(name, kind, Category::Project);
+        let node = &mut self.nodes[id.0];
+        node.kind = kind;
+        node.category = Category::Project;
+        node.location = Some(location);
+        node.parent = parent;
+        id
+    }
+
+    /// Adds the nodes, edges, ambiguities and errors of another graph. Nodes with the same
+    /// name become one.
+    pub fn extend(&mut self, other: CallGraph) {
+        let ids = other
+            .nodes
+            .iter()
+            .map(|node| match &node.location {
+                Some(location) => self.define(&node.name, node.kind, location.clone(), None),
+                None => self.add_node(&node.name, node.kind, node.category),
+            })
+            .collect::<Vec<_>>();
+        for (node, id) in other.nodes.iter().zip(&ids) {
+            let merged = &mut self.nodes[id.0];
+            if let Some(parent) = node.parent {
+                merged.parent = Some(ids[parent.0]);
+            }
+            merged.hidden_calls += node.hidden_calls;
+            merged.cumulative_time = merged.cumulative_time.or(node.cumulative_time);
+        }
+        self.edges.extend(other.edges.into_iter().map(|edge| Edge {
+            caller: ids[edge.caller.0],
+            callee: ids[edge.callee.0],
+            ..edge
+        }));
+        self.ambiguities
+            .extend(other.ambiguities.into_iter().map(|ambiguity| Ambiguity {
+                caller: ids[ambiguity.caller.0],
+                ..ambiguity
+            }));
+        self.errors.extend(other.errors);
+    }
+
+    /// Collapses edges with the same caller, callee and kind into one, keeping all of their
+    /// call sites.
+    pub fn merge_edges(&mut self) {
+        let mut merged: Vec<Edge> = Vec::new();
+        let mut index = HashMap::<_, usize>::new();
+        for edge in std::mem::take(&mut self.edges) {
+            match index.entry((edge.caller, edge.callee, edge.kind)) {
+                Entry::Occupied(entry) => {
+                    let merged = &mut merged[*entry.get()];
+                    merged.sites.extend(edge.sites);
+                    merged.count = match (merged.count, edge.count) {
+                        (Some(count), Some(more)) => Some(count + more),
+                        (count, more) => count.or(more),
+                    };
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(merged.len());
+                    merged.push(edge);
+                }
+            }
+        }
+        self.edges = merged;
+    }
+
+    /// Removes every node in one of the `hidden` categories, along with its edges.
+    pub fn hide(&mut self, hidden: &HashSet<Category>) {
+        let kept = self
+            .nodes
+            .iter()
+            .map(|node| !hidden.contains(&node.category))
+            .collect::<Vec<_>>();
+        if kept.iter().all(|&kept| kept) {
+            return;
+        }
+
+        for edge in &self.edges {
+            if !kept[edge.callee.0] && edge.kind.is_call() {
+                self.nodes[edge.caller.0].hidden_calls += edge.weight();
+            }
+        }
+        self.retain_nodes(|id, _| kept[id.0]);
+    }
+
+    /// Removes every node that `keep` returns false for, along with its edges.
+    pub fn retain_nodes(&mut self, mut keep: impl FnMut(NodeId, &Node) -> bool) {
+        let mut new_ids = vec![None; self.nodes.len()];
+        let mut nodes = Vec::new();
+        for (i, node) in std::mem::take(&mut self.nodes).into_iter().enumerate() {
+            if keep(NodeId(i), &node) {
+                new_ids[i] = Some(NodeId(nodes.len()));
+                nodes.push(node);
+            }
+        }
+        for node in &mut nodes {
+            node.parent = node.parent.and_then(|parent| new_ids[parent.0]);
+        }
+        self.edges.retain_mut(
+            |edge| match (new_ids[edge.caller.0], new_ids[edge.callee.0]) {
+                (Some(caller), Some(callee)) => {
+                    edge.caller = caller;
+                    edge.callee = callee;
+                    true
+                }
+                _ => false,
+            },
+        );
+        self.ambiguities
+            .retain_mut(|ambiguity| match new_ids[ambiguity.caller.0] {
+                Some(caller) => {
+                    ambiguity.caller = caller;
+                    true
+                }
+                None => false,
+            });
+        self.ids = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.name.clone(), NodeId(i)))
+            .collect();
+        self.nodes = nodes;
+    }
+}
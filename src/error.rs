@@ -0,0 +1,83 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+/// Why a file couldn't be added to a graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphError {
+    /// The file, or the directory it was looked for in, couldn't be read
+    Io {
+        file: PathBuf,
+        kind: io::ErrorKind,
+        message: String,
+    },
+    /// The file isn't valid source code
+    Syntax {
+        file: PathBuf,
+        /// 1-based
+        line: usize,
+        /// 1-based
+        column: usize,
+        message: String,
+    },
+    /// The file parsed, but uses something the generator can't handle
+    Unsupported {
+        file: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl GraphError {
+    pub fn io(file: &Path, error: &io::Error) -> Self {
+        Self::Io {
+            file: file.to_path_buf(),
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+
+    pub fn file(&self) -> &Path {
+        match self {
+            Self::Io { file, .. } | Self::Syntax { file, .. } | Self::Unsupported { file, .. } => {
+                file
+            }
+        }
+    }
+
+    /// The 1-based line and column the error is at, if it's about a part of the file.
+    pub fn position(&self) -> Option<(usize, usize)> {
+        match *self {
+            Self::Io { .. } => None,
+            Self::Syntax { line, column, .. } | Self::Unsupported { line, column, .. } => {
+                Some((line, column))
+            }
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::Io { message, .. }
+            | Self::Syntax { message, .. }
+            | Self::Unsupported { message, .. } => message,
+        }
+    }
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file().display())?;
+        if let Some((line, column)) = self.position() {
+            write!(f, ":{line}:{column}")?;
+        }
+        match self {
+            Self::Io { message, .. } => write!(f, ": couldn't read: {message}"),
+            Self::Syntax { message, .. } => write!(f, ": syntax error: {message}"),
+            Self::Unsupported { message, .. } => write!(f, ": unsupported: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
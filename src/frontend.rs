@@ -0,0 +1,159 @@
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    c_frontend::C,
+    call_graph::{CallGraph, Category},
+    error::GraphError,
+    generate_graph::Python,
+    js_frontend::JavaScript,
+    profile,
+    rust_frontend::Rust,
+    sources::{self, SourceFilter},
+};
+
+/// Options for building a graph.
+#[derive(Debug, Default, Clone)]
+pub struct GraphOptions {
+    /// Which files of a directory are analyzed
+    pub sources: SourceFilter,
+    /// Categories of nodes that are left out of the graph
+    pub hidden: HashSet<Category>,
+    /// Leaves out calls made by module-level code, including decorators and class bodies
+    pub exclude_module_scope: bool,
+    /// Leaves out files that can't be read or parsed and lists them in [`CallGraph::errors`],
+    /// instead of failing on the first one
+    pub lenient: bool,
+}
+
+/// Turns the source files of one language into a call graph.
+pub trait Frontend {
+    /// Extensions of the files this frontend reads, without the dot
+    fn extensions(&self) -> &'static [&'static str];
+
+    /// Builds one graph out of `(path, source)` pairs, so calls between the files are linked
+    /// up. `base` is the analyzed directory, if a directory was analyzed.
+    fn generate(
+        &self,
+        sources: Vec<(PathBuf, String)>,
+        base: Option<&Path>,
+        options: &GraphOptions,
+    ) -> Result<CallGraph, GraphError>;
+}
+
+/// Every supported language.
+pub fn frontends() -> Vec<Box<dyn Frontend>> {
+    vec![
+        Box::new(Python),
+        Box::new(Rust),
+        Box::new(JavaScript),
+        Box::new(C),
+    ]
+}
+
+/// Builds one graph out of every file under `path` that a frontend reads. `path` can also be
+/// a single file. Each language gets its own part of the graph.
+///
+/// A profile is loaded as the graph of the calls it recorded instead, see
+/// [`profile::load_profile`].
+pub fn generate_graph_from_path(
+    path: &Path,
+    options: &GraphOptions,
+) -> Result<CallGraph, GraphError> {
+    if profile::is_profile(path) {
+        let mut graph = profile::load_profile(path)?;
+        graph.hide(&options.hidden);
+        return Ok(graph);
+    }
+
+    let frontends = frontends();
+    let extensions = frontends
+        .iter()
+        .flat_map(|frontend| frontend.extensions())
+        .copied()
+        .collect::<Vec<_>>();
+    let files = sources::find_sources(path, &extensions, &options.sources).map_err(|error| {
+        let file = error.path().unwrap_or(path).to_path_buf();
+        // Walking only fails without an I/O error on a symlink loop
+        let message = error.to_string();
+        let error = error
+            .into_io_error()
+            .unwrap_or_else(|| io::Error::other(message));
+        GraphError::io(&file, &error)
+    })?;
+
+    let mut sources = frontends.iter().map(|_| Vec::new()).collect::<Vec<_>>();
+    let mut errors = Vec::new();
+    for file in files {
+        let Some(i) = frontends
+            .iter()
+            .position(|frontend| reads(&**frontend, &file))
+        else {
+            // Only a single file can be of an unknown kind
+            return Err(GraphError::Unsupported {
+                file,
+                line: 1,
+                column: 1,
+                message: "no frontend reads this kind of file".to_owned(),
+            });
+        };
+        match std::fs::read_to_string(&file) {
+            Ok(src) => sources[i].push((file, src)),
+            Err(error) if options.lenient => errors.push(GraphError::io(&file, &error)),
+            Err(error) => return Err(GraphError::io(&file, &error)),
+        }
+    }
+
+    let base = path.is_dir().then_some(path);
+    let mut graph = CallGraph::default();
+    for (frontend, sources) in frontends.iter().zip(sources) {
+        if !sources.is_empty() {
+            graph.extend(frontend.generate(sources, base, options)?);
+        }
+    }
+    graph.hide(&options.hidden);
+    errors.append(&mut graph.errors);
+    graph.errors = errors;
+    Ok(graph)
+}
+
+fn reads(frontend: &dyn Frontend, file: &Path) -> bool {
+    file.extension()
+        .is_some_and(|extension| frontend.extensions().iter().any(|&ext| extension == ext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn syntax_errors() {
+        let path = Path::new("assets/broken");
+        let error = generate_graph_from_path(path, &GraphOptions::default()).unwrap_err();
+        let GraphError::Syntax {
+            file, line, column, ..
+        } = &error
+        else {
+            panic!("expected a syntax error, got {error:?}");
+        };
+        assert_eq!(file, Path::new("assets/broken/syntax.py"));
+        // The call that isn't closed is only noticed at the next `def`
+        assert_eq!((*line, *column), (5, 1));
+        assert_eq!(error.position(), Some((5, 1)));
+
+        // Leniently, the other files still make up a graph
+        let options = GraphOptions {
+            lenient: true,
+            ..GraphOptions::default()
+        };
+        let graph = generate_graph_from_path(path, &options).unwrap();
+        assert_eq!(graph.errors, [error]);
+        let main = graph.find("main.main").unwrap();
+        let helper = graph.find("ok.helper").unwrap();
+        assert!(graph.calls_from(main).any(|edge| edge.callee == helper));
+        assert!(graph.find("syntax.parse").is_none());
+    }
+}
@@ -1,240 +1,2442 @@
-use anyhow::Result;
 use rustpython_ast::{
-    Expr, ExprAttribute, ExprAwait, ExprBinOp, ExprBoolOp, ExprCall, ExprCompare, ExprDict,
-    ExprDictComp, ExprFormattedValue, ExprGeneratorExp, ExprIfExp, ExprJoinedStr, ExprLambda,
-    ExprList, ExprListComp, ExprNamedExpr, ExprSet, ExprSetComp, ExprSlice, ExprStarred,
-    ExprSubscript, ExprTuple, ExprUnaryOp, ExprYield, ExprYieldFrom, Mod, Stmt, StmtAnnAssign,
-    StmtAssert, StmtAssign, StmtAsyncFunctionDef, StmtAugAssign, StmtDelete, StmtExpr, StmtFor,
-    StmtFunctionDef, StmtReturn, StmtTry, StmtWhile, StmtWith,
+    ArgWithDefault, Arguments, Comprehension, Constant, ExceptHandler, ExceptHandlerExceptHandler,
+    Expr, ExprAttribute, ExprAwait, ExprBinOp, ExprBoolOp, ExprCall, ExprCompare, ExprConstant,
+    ExprContext, ExprDict, ExprDictComp, ExprFormattedValue, ExprGeneratorExp, ExprIfExp,
+    ExprJoinedStr, ExprLambda, ExprList, ExprListComp, ExprName, ExprNamedExpr, ExprSet,
+    ExprSetComp, ExprSlice, ExprStarred, ExprSubscript, ExprTuple, ExprUnaryOp, ExprYield,
+    ExprYieldFrom, MatchCase, Mod, ModModule, Operator, Ranged, Stmt, StmtAnnAssign, StmtAssert,
+    StmtAssign, StmtAsyncFor, StmtAsyncFunctionDef, StmtAsyncWith, StmtAugAssign, StmtClassDef,
+    StmtDelete, StmtExpr, StmtFor, StmtFunctionDef, StmtIf, StmtMatch, StmtRaise, StmtReturn,
+    StmtTry, StmtTryStar, StmtWhile, StmtWith, WithItem,
+};
+use rustpython_parser::{
+    source_code::{LineIndex, SourceCode},
+    text_size::{TextLen, TextRange, TextSize},
+    Mode,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
 };
-use rustpython_parser::Mode;
-use std::collections::HashMap;
 
-pub fn generate_graph(src: &str, path: &str) -> Result<HashMap<String, Vec<String>>> {
-    let Mod::Module(module) = rustpython_parser::parse(src, Mode::Module, path)? else {
-        panic!();
-    };
+use crate::{
+    builtins,
+    call_graph::{
+        Ambiguity, CallGraph, CallSite, Category, Context, Edge, EdgeKind, Location, NodeId,
+        NodeKind,
+    },
+    error::GraphError,
+    frontend::{Frontend, GraphOptions},
+    imports::{self, ImportTable},
+};
+
+/// A call found while walking a scope, before it's added to the graph.
+struct Call {
+    /// Qualified name of the called function. Attribute calls keep their receiver, e.g.
+    /// `os.path.join`, so that unrelated methods with the same name stay apart
+    callee: String,
+    category: Category,
+    kind: EdgeKind,
+    site: CallSite,
+    /// Functions the call might go to instead, when the receiver's type is ambiguous
+    candidates: Vec<String>,
+}
+
+/// The Python frontend.
+pub struct Python;
+
+impl Frontend for Python {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["py"]
+    }
+
+    fn generate(
+        &self,
+        sources: Vec<(PathBuf, String)>,
+        base: Option<&Path>,
+        options: &GraphOptions,
+    ) -> Result<CallGraph, GraphError> {
+        generate_graph_from_sources(sources, base, options)
+    }
+}
+
+pub fn generate_graph(src: &str, path: &str) -> Result<CallGraph, GraphError> {
+    generate_graph_from_sources(
+        vec![(PathBuf::from(path), src.to_owned())],
+        None,
+        &GraphOptions::default(),
+    )
+}
+
+/// Builds a graph out of `(path, source)` pairs. Module names are found by looking for
+/// `__init__.py` files around each path, and directories between `base` and the top-level
+/// package are treated as namespace packages.
+fn generate_graph_from_sources(
+    sources: Vec<(PathBuf, String)>,
+    base: Option<&Path>,
+    options: &GraphOptions,
+) -> Result<CallGraph, GraphError> {
+    let mut modules = Vec::new();
+    let mut errors = Vec::new();
+    for (path, src) in sources {
+        let module = match parse_module(&path, &src) {
+            Ok(module) => module,
+            Err(error) if options.lenient => {
+                errors.push(error);
+                continue;
+            }
+            Err(error) => return Err(error),
+        };
+        let (name, root) = imports::qualified_module_name(&path, base);
+        let is_package = path.file_stem().is_some_and(|stem| stem == "__init__");
+        let mut imports = ImportTable::default();
+        collect_imports(&module.body, &name, is_package, &mut imports);
+        modules.push(ParsedModule {
+            lines: LineIndex::from_source_text(&src),
+            path,
+            src,
+            name,
+            is_package,
+            root,
+            imports,
+            body: module.body,
+        });
+    }
+
+    // Re-exports are looked up in the analyzed packages first, and then on disk
+    let package_imports = modules
+        .iter()
+        .filter(|module| module.is_package)
+        .map(|module| (module.name.clone(), module.imports.clone()))
+        .collect::<HashMap<_, _>>();
+    let analyzed = modules
+        .iter()
+        .map(|module| module.name.clone())
+        .collect::<HashSet<_>>();
+
+    for ParsedModule { root, imports, .. } in &mut modules {
+        imports.follow_reexports(|package| {
+            if let Some(imports) = package_imports.get(package) {
+                return Some(imports.clone());
+            }
+            let init_path = imports::package_init(root, package);
+            let src = std::fs::read_to_string(&init_path).ok()?;
+            let Mod::Module(init) =
+                rustpython_parser::parse(&src, Mode::Module, &init_path.to_string_lossy()).ok()?
+            else {
+                return None;
+            };
+            let mut init_imports = ImportTable::default();
+            collect_imports(&init.body, package, true, &mut init_imports);
+            Some(init_imports)
+        });
+    }
+
+    // Variables can hold instances of classes from any module
+    let mut definitions = Definitions::default();
+    let unknown = Definitions::default();
+    for parsed in &modules {
+        let module = parsed.module(&analyzed, &unknown, options);
+        let module_scope = Scope::module(&module, &parsed.body);
+        collect_definitions(&parsed.body, None, &module_scope, &mut definitions);
+    }
+
+    let mut graph = CallGraph::default();
+    for mut parsed in modules {
+        let body = std::mem::take(&mut parsed.body);
+        let module = parsed.module(&analyzed, &definitions, options);
+        let module_scope = Scope::module(&module, &body);
+        for stmt in body {
+            build_graph_from_stmt(stmt, &module_scope, Context::default(), &mut graph);
+        }
+    }
+
+    link_definitions(&mut graph, &definitions);
+    graph.merge_edges();
+    graph.errors = errors;
+
+    Ok(graph)
+}
+
+/// Parses a file, which has to hold a module.
+fn parse_module(path: &Path, src: &str) -> Result<ModModule, GraphError> {
+    match rustpython_parser::parse(src, Mode::Module, &path.to_string_lossy()) {
+        Ok(Mod::Module(module)) => Ok(module),
+        Ok(_) => Err(GraphError::Unsupported {
+            file: path.to_path_buf(),
+            line: 1,
+            column: 1,
+            message: "expected a module".to_owned(),
+        }),
+        Err(error) => {
+            let lines = LineIndex::from_source_text(src);
+            let location = SourceCode::new(src, &lines).source_location(error.offset);
+            Err(GraphError::Syntax {
+                file: path.to_path_buf(),
+                line: location.row.to_usize(),
+                column: location.column.to_usize(),
+                message: error.error.to_string(),
+            })
+        }
+    }
+}
+
+/// Fixes up edges that depend on what is defined anywhere in the analyzed code, since things
+/// can be used before they are defined.
+fn link_definitions(graph: &mut CallGraph, definitions: &Definitions) {
+    // Calling a class runs its `__new__` and `__init__`, which may be inherited
+    for edge in std::mem::take(&mut graph.edges) {
+        let callee = graph.node(edge.callee);
+        if !matches!(edge.kind, EdgeKind::Call | EdgeKind::AttributeCall)
+            || callee.kind != NodeKind::Class
+        {
+            graph.edges.push(edge);
+            continue;
+        }
+        let constructors = ["__new__", "__init__"]
+            .into_iter()
+            .filter_map(|method| graph.find(&definitions.lookup(&callee.name, method)?))
+            .filter(|&id| graph.node(id).kind == NodeKind::Method)
+            .collect::<Vec<_>>();
+        if constructors.is_empty() {
+            graph.edges.push(Edge {
+                kind: EdgeKind::Instantiation,
+                ..edge
+            });
+            continue;
+        }
+        for constructor in constructors {
+            graph.edges.push(Edge {
+                callee: constructor,
+                kind: EdgeKind::Instantiation,
+                ..edge.clone()
+            });
+        }
+    }
+
+    // A call to a method may end up in any override of it. Overrides are found in the MRO of
+    // every class, since with multiple inheritance they can come from a sibling class
+    let mut classes = definitions.classes.keys().collect::<Vec<_>>();
+    classes.sort();
+    let mut dispatches = HashSet::new();
+    for class in classes {
+        let mro = definitions.mro(class);
+        let mut names = definitions
+            .methods
+            .iter()
+            .filter_map(|method| method.rsplit_once('.'))
+            .filter(|(owner, _)| mro.iter().any(|class| class == owner))
+            .map(|(_, name)| name)
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        for name in names {
+            let overrides = mro
+                .iter()
+                .map(|class| format!("{class}.{name}"))
+                .filter_map(|method| graph.find(&method))
+                .filter(|&id| graph.node(id).kind == NodeKind::Method)
+                .collect::<Vec<_>>();
+            for pair in overrides.windows(2) {
+                dispatches.insert((pair[1], pair[0]));
+            }
+        }
+    }
+    let mut dispatches = dispatches.into_iter().collect::<Vec<_>>();
+    dispatches.sort();
+    for (overridden, method) in dispatches {
+        let Some(location) = &graph.node(method).location else {
+            continue;
+        };
+        let site = CallSite {
+            receiver: None,
+            line: location.start_line,
+            column: 1,
+            context: Context::default(),
+        };
+        graph.edges.push(Edge {
+            caller: overridden,
+            callee: method,
+            kind: EdgeKind::Dispatch,
+            sites: vec![site],
+            count: None,
+        });
+    }
 
-    let mut graph = HashMap::new();
-    let mut current_name;
-    for stmt in module.body {
-        current_name = "...".to_owned();
+    // Any name can be passed around and the types of objects are guessed, so references and
+    // special methods are only kept if they turned out to be defined. Nodes that were only
+    // added for them go away as well
+    let functions = graph
+        .nodes()
+        .filter(|(_, node)| {
+            node.location.is_some()
+                && matches!(
+                    node.kind,
+                    NodeKind::Function | NodeKind::Method | NodeKind::Lambda
+                )
+        })
+        .map(|(id, _)| id)
+        .collect::<HashSet<_>>();
+    graph.edges.retain(|edge| {
+        !matches!(edge.kind, EdgeKind::Reference | EdgeKind::Implicit)
+            || functions.contains(&edge.callee)
+    });
+    let linked = graph
+        .edges
+        .iter()
+        .flat_map(|edge| [edge.caller, edge.callee])
+        .collect::<HashSet<_>>();
+    graph.retain_nodes(|id, node| node.location.is_some() || linked.contains(&id));
+}
+
+/// A source file that was parsed, but whose calls haven't been walked yet.
+struct ParsedModule {
+    path: PathBuf,
+    src: String,
+    name: String,
+    is_package: bool,
+    /// The directory the module's top-level package is in
+    root: PathBuf,
+    imports: ImportTable,
+    lines: LineIndex,
+    body: Vec<Stmt>,
+}
+
+impl ParsedModule {
+    fn module<'a>(
+        &'a self,
+        analyzed: &'a HashSet<String>,
+        definitions: &'a Definitions,
+        options: &GraphOptions,
+    ) -> Module<'a> {
+        Module {
+            name: &self.name,
+            imports: &self.imports,
+            analyzed,
+            definitions,
+            include_scope: !options.exclude_module_scope,
+            path: &self.path,
+            src: &self.src,
+            lines: &self.lines,
+        }
+    }
+}
+
+/// What the analyzed code defines, used to infer the types of variables and to look up
+/// inherited methods.
+#[derive(Default)]
+struct Definitions {
+    /// Every class, mapped to the qualified names of its bases
+    classes: HashMap<String, Vec<String>>,
+    /// Qualified names of every method
+    methods: HashSet<String>,
+    /// Unqualified names of every method, to tell project methods apart from builtin ones
+    method_names: HashSet<String>,
+    /// The annotated return types of functions, qualified but not necessarily classes
+    returns: HashMap<String, String>,
+}
+
+impl Definitions {
+    /// The method resolution order of a class, leaving out bases outside of the analyzed code.
+    fn mro(&self, class: &str) -> Vec<String> {
+        self.linearize(class, &mut Vec::new())
+    }
+
+    /// C3 linearization, as done by Python. `visiting` guards against classes that
+    /// (through aliases) inherit from themselves.
+    fn linearize(&self, class: &str, visiting: &mut Vec<String>) -> Vec<String> {
+        let bases = match self.classes.get(class) {
+            Some(bases) if !visiting.iter().any(|visited| visited == class) => bases
+                .iter()
+                .filter(|base| self.classes.contains_key(*base))
+                .cloned()
+                .collect::<Vec<_>>(),
+            _ => return vec![class.to_owned()],
+        };
+        visiting.push(class.to_owned());
+        let mut sequences = bases
+            .iter()
+            .map(|base| self.linearize(base, visiting))
+            .collect::<Vec<_>>();
+        visiting.pop();
+        sequences.push(bases);
+
+        let mut mro = vec![class.to_owned()];
+        loop {
+            sequences.retain(|sequence| !sequence.is_empty());
+            if sequences.is_empty() {
+                return mro;
+            }
+            let head = sequences
+                .iter()
+                .map(|sequence| &sequence[0])
+                .find(|head| {
+                    !sequences
+                        .iter()
+                        .any(|sequence| sequence[1..].contains(head))
+                })
+                .cloned();
+            let Some(head) = head else {
+                // Python refuses to create such a class, so any order will do
+                for class in sequences.concat() {
+                    if !mro.contains(&class) {
+                        mro.push(class);
+                    }
+                }
+                return mro;
+            };
+            for sequence in &mut sequences {
+                if sequence[0] == head {
+                    sequence.remove(0);
+                }
+            }
+            mro.push(head);
+        }
+    }
+
+    /// The first definition of `method` in `mro`.
+    fn find_method<'b>(
+        &self,
+        mro: impl IntoIterator<Item = &'b String>,
+        method: &str,
+    ) -> Option<String> {
+        mro.into_iter()
+            .map(|class| format!("{class}.{method}"))
+            .find(|qualified| self.methods.contains(qualified))
+    }
+
+    /// The method that runs when `method` is looked up on an instance of `class`.
+    fn lookup(&self, class: &str, method: &str) -> Option<String> {
+        self.find_method(&self.mro(class), method)
+    }
+}
+
+/// Information shared by every scope of a module.
+struct Module<'a> {
+    /// Dotted module name, e.g. `pkg.utils`
+    name: &'a str,
+    imports: &'a ImportTable,
+    /// Names of every module in the graph
+    analyzed: &'a HashSet<String>,
+    definitions: &'a Definitions,
+    /// Whether calls made by module-level code are part of the graph
+    include_scope: bool,
+    path: &'a Path,
+    src: &'a str,
+    lines: &'a LineIndex,
+}
+
+impl Module<'_> {
+    /// The 1-based line and column of a byte offset.
+    fn locate(&self, offset: TextSize) -> (usize, usize) {
+        let location = SourceCode::new(self.src, self.lines).source_location(offset);
+        (location.row.to_usize(), location.column.to_usize())
+    }
+
+    fn call_site(&self, receiver: Option<String>, offset: TextSize) -> CallSite {
+        let (line, column) = self.locate(offset);
+        CallSite {
+            receiver,
+            line,
+            column,
+            context: Context::default(),
+        }
+    }
+
+    fn location(&self, range: TextRange) -> Location {
+        Location {
+            file: self.path.to_path_buf(),
+            start_line: self.locate(range.start()).0,
+            end_line: self.locate(range.end()).0,
+        }
+    }
+
+    /// Whether a fully qualified name refers to something in the analyzed code.
+    fn is_analyzed(&self, target: &str) -> bool {
+        let mut module = target;
+        while let Some((parent, _)) = module.rsplit_once('.') {
+            if self.analyzed.contains(parent) {
+                return true;
+            }
+            module = parent;
+        }
+        false
+    }
+}
+
+/// The function that calls are currently attributed to.
+struct Scope<'a> {
+    name: String,
+    /// The class this function is a method of, used to resolve `self.foo()` and `cls.foo()`
+    class: Option<&'a Class>,
+    /// The enclosing function scope. Only the module scope has no parent
+    parent: Option<&'a Scope<'a>>,
+    /// Functions and classes defined in this function's body
+    locals: HashSet<String>,
+    /// Variables assigned in this function's body, including its parameters
+    variables: HashMap<String, Variable>,
+    /// The class whose body is being run, along with its node. Functions and classes defined
+    /// in it are members of that class
+    class_body: Option<(&'a Class, NodeId)>,
+    module: &'a Module<'a>,
+}
+
+impl<'a> Scope<'a> {
+    fn module(module: &'a Module<'a>, body: &[Stmt]) -> Self {
+        let mut locals = HashSet::new();
+        collect_local_defs(body, &mut locals);
+        let mut scope = Self {
+            name: format!("<module {}>", module.name),
+            class: None,
+            parent: None,
+            locals,
+            variables: HashMap::new(),
+            class_body: None,
+            module,
+        };
+        let mut variables = HashMap::new();
+        scope.collect_assignments(body, &mut variables);
+        scope.variables = variables;
+        scope
+    }
+
+    fn function(
+        name: String,
+        class: Option<&'a Class>,
+        parent: &'a Scope<'a>,
+        args: &Arguments,
+        body: &[Stmt],
+    ) -> Self {
+        let mut locals = HashSet::new();
+        collect_local_defs(body, &mut locals);
+        let mut scope = Self {
+            name,
+            class,
+            parent: Some(parent),
+            locals,
+            variables: HashMap::new(),
+            class_body: None,
+            module: parent.module,
+        };
+        let mut variables = HashMap::new();
+        scope.collect_parameters(args, &mut variables);
+        scope.collect_assignments(body, &mut variables);
+        scope.variables = variables;
+        scope
+    }
+
+    /// The scope a class body runs in. It sees the same names as `scope` and its calls are
+    /// made by `scope`, but what it defines belongs to the class.
+    fn class_body(scope: &'a Scope<'a>, class: &'a Class, id: NodeId) -> Self {
+        Self {
+            name: scope.name.clone(),
+            class: scope.class,
+            parent: scope.parent,
+            locals: scope.locals.clone(),
+            variables: scope.variables.clone(),
+            class_body: Some((class, id)),
+            module: scope.module,
+        }
+    }
+
+    fn collect_parameters(&self, args: &Arguments, variables: &mut HashMap<String, Variable>) {
+        for ArgWithDefault { def, .. } in args
+            .posonlyargs
+            .iter()
+            .chain(&args.args)
+            .chain(&args.kwonlyargs)
+        {
+            let class = def
+                .annotation
+                .as_deref()
+                .and_then(|annotation| self.annotation_class(annotation));
+            variables
+                .entry(def.arg.to_string())
+                .or_default()
+                .assign(class);
+        }
+        for arg in args.vararg.iter().chain(&args.kwarg) {
+            variables
+                .entry(arg.arg.to_string())
+                .or_default()
+                .assign(None);
+        }
+    }
+
+    /// Collects what every variable is assigned in `body`, regardless of the order the
+    /// assignments run in. Variables that are bound in other ways, e.g. by `for` loops, have
+    /// an unknown type.
+    fn collect_assignments(&self, body: &[Stmt], variables: &mut HashMap<String, Variable>) {
+        for stmt in body {
+            match stmt {
+                Stmt::Assign(StmtAssign { targets, value, .. }) => {
+                    // `x = None` is usually a placeholder for an instance assigned later
+                    if is_none(value) {
+                        continue;
+                    }
+                    let class = self.expr_class(value);
+                    for target in targets {
+                        match target {
+                            Expr::Name(ExprName { id, .. }) => {
+                                variables
+                                    .entry(id.to_string())
+                                    .or_default()
+                                    .assign(class.clone());
+                            }
+                            _ => bind_unknown(target, variables),
+                        }
+                    }
+                }
+                Stmt::AnnAssign(StmtAnnAssign {
+                    target,
+                    annotation,
+                    value,
+                    ..
+                }) => {
+                    if let Expr::Name(ExprName { id, .. }) = &**target {
+                        let class = self.annotation_class(annotation).or_else(|| {
+                            value
+                                .as_deref()
+                                .filter(|value| !is_none(value))
+                                .and_then(|value| self.expr_class(value))
+                        });
+                        variables.entry(id.to_string()).or_default().assign(class);
+                    }
+                }
+                Stmt::For(StmtFor { target, .. }) | Stmt::AsyncFor(StmtAsyncFor { target, .. }) => {
+                    bind_unknown(target, variables);
+                }
+                Stmt::With(StmtWith { items, .. })
+                | Stmt::AsyncWith(StmtAsyncWith { items, .. }) => {
+                    for item in items {
+                        if let Some(vars) = &item.optional_vars {
+                            bind_unknown(vars, variables);
+                        }
+                    }
+                }
+                Stmt::Try(StmtTry { handlers, .. })
+                | Stmt::TryStar(StmtTryStar { handlers, .. }) => {
+                    for ExceptHandler::ExceptHandler(handler) in handlers {
+                        if let Some(name) = &handler.name {
+                            variables.entry(name.to_string()).or_default().assign(None);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            for body in nested_bodies(stmt) {
+                self.collect_assignments(body, variables);
+            }
+        }
+    }
+
+    /// The variable a name refers to, following Python's lexical scoping.
+    fn variable(&self, name: &str) -> Option<&Variable> {
+        let mut scope = Some(self);
+        while let Some(current) = scope {
+            if let Some(variable) = current.variables.get(name) {
+                return Some(variable);
+            }
+            if current.locals.contains(name) {
+                return None;
+            }
+            scope = current.parent;
+        }
+        None
+    }
+
+    /// The scope whose parameters or variables include `name`, following Python's lexical
+    /// scoping.
+    fn binding_scope(&self, name: &str) -> Option<&Scope<'a>> {
+        let mut scope = Some(self);
+        while let Some(current) = scope {
+            if current.variables.contains_key(name) {
+                return Some(current);
+            }
+            if current.locals.contains(name) {
+                return None;
+            }
+            scope = current.parent;
+        }
+        None
+    }
+
+    /// The qualified name of the type an annotation refers to, which may not be a class.
+    /// Handles forward references in strings, and `Optional[...]` or `... | None`.
+    fn annotation_type(&self, annotation: &Expr) -> Option<String> {
+        match annotation {
+            Expr::Name(_) | Expr::Attribute(_) => {
+                Some(self.resolve(&receiver_chain(annotation))?.0)
+            }
+            Expr::Constant(ExprConstant {
+                value: Constant::Str(name),
+                ..
+            }) => Some(self.resolve(name)?.0),
+            Expr::Subscript(ExprSubscript { value, slice, .. })
+                if self.annotation_type(value).as_deref() == Some("typing.Optional") =>
+            {
+                self.annotation_type(slice)
+            }
+            Expr::BinOp(ExprBinOp {
+                left,
+                op: Operator::BitOr,
+                right,
+                ..
+            }) => match (is_none(left), is_none(right)) {
+                (false, true) => self.annotation_type(left),
+                (true, false) => self.annotation_type(right),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn annotation_class(&self, annotation: &Expr) -> Option<String> {
+        self.annotation_type(annotation)
+            .filter(|name| self.module.definitions.classes.contains_key(name))
+    }
+
+    /// The class of the object an expression evaluates to, if it's known. Calls to classes and
+    /// to functions with an annotated return type are recognized.
+    fn expr_class(&self, expr: &Expr) -> Option<String> {
+        let Expr::Call(ExprCall { func, .. }) = expr else {
+            return None;
+        };
+        let callee = match &**func {
+            Expr::Attribute(ExprAttribute { value, attr, .. }) => self
+                .bound_method(value, attr)
+                .or_else(|| Some(self.resolve(&receiver_chain(func))?.0))?,
+            Expr::Name(ExprName { id, .. }) if id == "cls" && self.class.is_some() => {
+                self.class?.name.clone()
+            }
+            Expr::Name(ExprName { id, .. }) => self.resolve(id)?.0,
+            _ => return None,
+        };
+        let definitions = self.module.definitions;
+        if definitions.classes.contains_key(&callee) {
+            return Some(callee);
+        }
+        definitions
+            .returns
+            .get(&callee)
+            .filter(|name| definitions.classes.contains_key(*name))
+            .cloned()
+    }
+
+    fn is_module(&self) -> bool {
+        self.parent.is_none()
+    }
+
+    /// The qualified name of something defined directly in this scope.
+    fn qualify(&self, name: &str) -> String {
+        if self.is_module() {
+            format!("{}.{name}", self.module.name)
+        } else {
+            format!("{}.<locals>.{name}", self.name)
+        }
+    }
+
+    /// Resolves a dotted name to the closest enclosing definition of its first part, following
+    /// Python's lexical scoping, and then to the module's imports. Returns `None` for names
+    /// that are neither defined nor imported.
+    fn resolve(&self, dotted: &str) -> Option<(String, Category)> {
+        let (first, rest) = match dotted.split_once('.') {
+            Some((first, rest)) => (first, Some(rest)),
+            None => (dotted, None),
+        };
+        let mut scope = Some(self);
+        while let Some(current) = scope {
+            if current.locals.contains(first) {
+                let qualified = current.qualify(first);
+                return match rest {
+                    Some(rest) => Some((format!("{qualified}.{rest}"), Category::Project)),
+                    None => Some((qualified, Category::Project)),
+                };
+            }
+            scope = current.parent;
+        }
+        let target = self.module.imports.resolve(dotted)?;
+        let category = if self.module.is_analyzed(&target) {
+            Category::Project
+        } else if builtins::is_stdlib(&target) {
+            Category::Stdlib
+        } else {
+            Category::ThirdParty
+        };
+        Some((target, category))
+    }
+
+    fn call(&self, name: &str, offset: TextSize) -> Call {
+        // `cls()` creates an instance of the class the method belongs to
+        let class = self.class.filter(|_| name == "cls");
+        let resolved = match class {
+            Some(class) => Some((class.name.clone(), Category::Project)),
+            None => self.resolve(name),
+        };
+        let (callee, category) = resolved.unwrap_or_else(|| match self.binding_scope(name) {
+            // Parameters and variables hold something else in every function, so they are
+            // kept apart by the scope that binds them
+            Some(scope) if scope.is_module() => {
+                (format!("{}.<{name}>", self.module.name), Category::Project)
+            }
+            Some(scope) => (format!("{}.<{name}>", scope.name), Category::Project),
+            None if builtins::is_builtin(name) => (name.to_owned(), Category::Builtin),
+            None => (name.to_owned(), Category::Project),
+        });
+        Call {
+            callee,
+            category,
+            kind: EdgeKind::Call,
+            site: self.module.call_site(None, offset),
+            candidates: Vec::new(),
+        }
+    }
+
+    /// The qualified name of a method accessed through `self`, `cls` or `super()`, e.g.
+    /// `self.save`. Methods are looked up in base classes following the MRO.
+    fn bound_method(&self, value: &Expr, attr: &str) -> Option<String> {
+        let class = self.class?;
+        let definitions = self.module.definitions;
+        match value {
+            Expr::Name(ExprName { id, .. }) if matches!(id.as_str(), "self" | "cls") => {
+                definitions.lookup(&class.name, attr)
+            }
+            // `super(Class, self)` starts looking after `Class`, `super()` after the class of
+            // the method it's in
+            Expr::Call(ExprCall { func, args, .. }) if matches!(&**func, Expr::Name(ExprName { id, .. }) if id == "super") =>
+            {
+                let start = match args.first() {
+                    Some(start) => self.resolve(&receiver_chain(start))?.0,
+                    None => class.name.clone(),
+                };
+                let mro = definitions.mro(&class.name);
+                let position = mro.iter().position(|class| *class == start)?;
+                definitions.find_method(&mro[position + 1..], attr)
+            }
+            _ => None,
+        }
+    }
+
+    /// A function that is passed as a value rather than called, e.g. the `handler` in
+    /// `button.on_click(handler)`. Returns `None` for names that aren't defined or imported.
+    fn reference(&self, expr: &Expr) -> Option<Call> {
+        let (callee, category) = match expr {
+            Expr::Attribute(ExprAttribute { value, attr, .. }) => {
+                match self.bound_method(value, attr) {
+                    Some(method) => (method, Category::Project),
+                    None => self.resolve(&receiver_chain(expr))?,
+                }
+            }
+            Expr::Name(_) => self.resolve(&receiver_chain(expr))?,
+            _ => return None,
+        };
+        Some(Call {
+            callee,
+            category,
+            kind: EdgeKind::Reference,
+            site: self.module.call_site(None, expr.start()),
+            candidates: Vec::new(),
+        })
+    }
+
+    /// The class of the object an expression evaluates to, including through variables.
+    fn instance_type(&self, expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Name(ExprName { id, .. }) => self.variable(id)?.class().map(str::to_owned),
+            _ => self.expr_class(expr),
+        }
+    }
+
+    /// A special method that Python calls on an instance of `class`, e.g. `__enter__` for a
+    /// `with` block. It's only kept if the class turns out to define it.
+    fn implicit_call(&self, class: &str, method: &str, offset: TextSize) -> Call {
+        Call {
+            callee: self
+                .module
+                .definitions
+                .lookup(class, method)
+                .unwrap_or_else(|| format!("{class}.{method}")),
+            category: Category::Project,
+            kind: EdgeKind::Implicit,
+            site: self.module.call_site(None, offset),
+            candidates: Vec::new(),
+        }
+    }
+
+    /// A call to a method of an instance of `class`.
+    fn instance_method_call(
+        &self,
+        class: &str,
+        receiver: String,
+        attr: &str,
+        offset: TextSize,
+    ) -> Call {
+        Call {
+            callee: self
+                .module
+                .definitions
+                .lookup(class, attr)
+                .unwrap_or_else(|| format!("{class}.{attr}")),
+            category: Category::Project,
+            kind: EdgeKind::AttributeCall,
+            site: self.module.call_site(Some(receiver), offset),
+            candidates: Vec::new(),
+        }
+    }
+
+    fn method_call(&self, receiver: String, attr: &str, offset: TextSize) -> Call {
+        let mut candidates = Vec::new();
+        if let Some(variable) = self.variable(&receiver) {
+            let definitions = self.module.definitions;
+            if let Some(class) = variable.class() {
+                return self.instance_method_call(class, receiver, attr, offset);
+            }
+            // Guessing one of the classes would hide that the others are possible
+            candidates = variable
+                .classes
+                .iter()
+                .map(|class| {
+                    definitions
+                        .lookup(class, attr)
+                        .unwrap_or_else(|| format!("{class}.{attr}"))
+                })
+                .collect();
+        }
+        let (qualified, category) = self.resolve(&receiver).unwrap_or_else(|| {
+            // The root may be called or indexed, e.g. `super().__init__` or `open(path).read`
+            let first = receiver.split(['.', '(', '[']).next().unwrap_or(&receiver);
+            if builtins::is_builtin(first) {
+                return (receiver.clone(), Category::Builtin);
+            }
+            // The receiver's type isn't known, so this is only a guess. A method the project
+            // defines is more likely than a builtin one with the same name
+            let category = if !self.module.definitions.method_names.contains(attr)
+                && builtins::is_builtin_method(attr)
+            {
+                Category::Builtin
+            } else {
+                Category::Project
+            };
+            // Receivers like `self.db` mean something else in every class, and `<expr>` in
+            // every function, so they're kept apart by where they are used
+            let owner = if receiver.contains("<expr>") && !self.is_module() {
+                &self.name
+            } else {
+                self.class.map_or(self.module.name, |class| &class.name)
+            };
+            let placeholder = match receiver.as_str() {
+                "<expr>" => receiver.clone(),
+                _ => format!("<{receiver}>"),
+            };
+            (format!("{owner}.{placeholder}"), category)
+        });
+        Call {
+            callee: format!("{qualified}.{attr}"),
+            category,
+            kind: EdgeKind::AttributeCall,
+            site: self.module.call_site(Some(receiver), offset),
+            candidates,
+        }
+    }
+}
+
+/// What a variable is assigned anywhere in its scope.
+#[derive(Clone, Default)]
+struct Variable {
+    classes: Vec<String>,
+    /// Whether it is also assigned something of an unknown type
+    unknown: bool,
+}
+
+impl Variable {
+    fn assign(&mut self, class: Option<String>) {
+        match class {
+            Some(class) if !self.classes.contains(&class) => self.classes.push(class),
+            Some(_) => {}
+            None => self.unknown = true,
+        }
+    }
+
+    /// The variable's class, if it is always assigned instances of the same one.
+    fn class(&self) -> Option<&str> {
+        match self.classes.as_slice() {
+            [class] if !self.unknown => Some(class),
+            _ => None,
+        }
+    }
+}
+
+fn is_none(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Constant(ExprConstant {
+            value: Constant::None,
+            ..
+        })
+    )
+}
+
+/// Marks every name bound by an assignment target as having an unknown type, e.g. both names
+/// in `a, b = pair`.
+fn bind_unknown(target: &Expr, variables: &mut HashMap<String, Variable>) {
+    match target {
+        Expr::Name(ExprName { id, .. }) => {
+            variables.entry(id.to_string()).or_default().assign(None)
+        }
+        Expr::Tuple(ExprTuple { elts, .. }) | Expr::List(ExprList { elts, .. }) => {
+            for elt in elts {
+                bind_unknown(elt, variables);
+            }
+        }
+        Expr::Starred(ExprStarred { value, .. }) => bind_unknown(value, variables),
+        _ => {}
+    }
+}
+
+/// Collects the classes in `body` and the return types of its functions, including nested
+/// ones. `parent` is the enclosing definition and whether it's a function. Annotations are
+/// resolved in `module_scope`.
+fn collect_definitions(
+    body: &[Stmt],
+    parent: Option<(&str, bool)>,
+    module_scope: &Scope,
+    definitions: &mut Definitions,
+) {
+    for stmt in body {
+        let qualify = |name: &str| match parent {
+            None => module_scope.qualify(name),
+            Some((parent, true)) => format!("{parent}.<locals>.{name}"),
+            Some((parent, false)) => format!("{parent}.{name}"),
+        };
         match stmt {
-            Stmt::FunctionDef(StmtFunctionDef { name, body, .. })
-            | Stmt::AsyncFunctionDef(StmtAsyncFunctionDef { name, body, .. }) => {
-                current_name = name.to_string();
-                graph.insert(current_name.clone(), Vec::new());
-                for stmt in body {
-                    build_graph_from_stmt(stmt, current_name.clone(), &mut graph);
+            Stmt::FunctionDef(StmtFunctionDef {
+                name,
+                returns,
+                body,
+                ..
+            })
+            | Stmt::AsyncFunctionDef(StmtAsyncFunctionDef {
+                name,
+                returns,
+                body,
+                ..
+            }) => {
+                if let Some((_, false)) = parent {
+                    definitions.methods.insert(qualify(name));
+                    definitions.method_names.insert(name.to_string());
                 }
+                let name = qualify(name);
+                if let Some(returns) = returns
+                    .as_deref()
+                    .and_then(|returns| module_scope.annotation_type(returns))
+                {
+                    definitions.returns.insert(name.clone(), returns);
+                }
+                collect_definitions(body, Some((&name, true)), module_scope, definitions);
+            }
+            Stmt::ClassDef(StmtClassDef {
+                name, bases, body, ..
+            }) => {
+                let name = qualify(name);
+                collect_definitions(body, Some((&name, false)), module_scope, definitions);
+                let bases = bases
+                    .iter()
+                    .filter_map(|base| module_scope.annotation_type(base))
+                    .collect();
+                definitions.classes.insert(name, bases);
             }
             _ => {
-                build_graph_from_stmt(stmt, current_name.clone(), &mut graph);
+                for body in nested_bodies(stmt) {
+                    collect_definitions(body, parent, module_scope, definitions);
+                }
             }
         }
     }
+}
 
-    Ok(graph)
+/// The class a method belongs to.
+struct Class {
+    /// Qualified name, e.g. `Outer.Inner` for nested classes
+    name: String,
 }
 
-fn build_graph_from_stmt(stmt: Stmt, func_name: String, graph: &mut HashMap<String, Vec<String>>) {
+/// Collects the names of functions and classes defined in `body`, including inside of
+/// compound statements, but not inside of the definitions themselves.
+fn collect_local_defs(body: &[Stmt], locals: &mut HashSet<String>) {
+    for stmt in body {
+        match stmt {
+            Stmt::FunctionDef(StmtFunctionDef { name, .. })
+            | Stmt::AsyncFunctionDef(StmtAsyncFunctionDef { name, .. })
+            | Stmt::ClassDef(StmtClassDef { name, .. }) => {
+                locals.insert(name.to_string());
+            }
+            _ => {
+                for body in nested_bodies(stmt) {
+                    collect_local_defs(body, locals);
+                }
+            }
+        }
+    }
+}
+
+/// Collects every import in `body`, including ones inside of functions and classes.
+fn collect_imports(body: &[Stmt], module: &str, is_package: bool, imports: &mut ImportTable) {
+    for stmt in body {
+        match stmt {
+            Stmt::Import(_) | Stmt::ImportFrom(_) => imports.add(stmt, module, is_package),
+            Stmt::FunctionDef(StmtFunctionDef { body, .. })
+            | Stmt::AsyncFunctionDef(StmtAsyncFunctionDef { body, .. })
+            | Stmt::ClassDef(StmtClassDef { body, .. }) => {
+                collect_imports(body, module, is_package, imports);
+            }
+            _ => {
+                for body in nested_bodies(stmt) {
+                    collect_imports(body, module, is_package, imports);
+                }
+            }
+        }
+    }
+}
+
+/// The blocks of statements inside of a compound statement, not counting definitions.
+fn nested_bodies(stmt: &Stmt) -> Vec<&[Stmt]> {
     match stmt {
-        Stmt::Expr(StmtExpr { value, .. }) => {
-            build_graph(*value, func_name.to_string(), graph);
+        Stmt::For(StmtFor { body, orelse, .. })
+        | Stmt::AsyncFor(StmtAsyncFor { body, orelse, .. })
+        | Stmt::While(StmtWhile { body, orelse, .. })
+        | Stmt::If(StmtIf { body, orelse, .. }) => vec![body, orelse],
+        Stmt::With(StmtWith { body, .. }) | Stmt::AsyncWith(StmtAsyncWith { body, .. }) => {
+            vec![body]
         }
-        Stmt::Return(StmtReturn { value, .. }) => {
-            if let Some(value) = value {
-                build_graph(*value, func_name.to_string(), graph);
+        Stmt::Try(StmtTry {
+            body,
+            handlers,
+            orelse,
+            finalbody,
+            ..
+        })
+        | Stmt::TryStar(StmtTryStar {
+            body,
+            handlers,
+            orelse,
+            finalbody,
+            ..
+        }) => {
+            let mut bodies = vec![body.as_slice()];
+            for ExceptHandler::ExceptHandler(handler) in handlers {
+                bodies.push(&handler.body);
             }
+            bodies.push(orelse);
+            bodies.push(finalbody);
+            bodies
+        }
+        Stmt::Match(StmtMatch { cases, .. }) => cases.iter().map(|case| &*case.body).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The node that definitions directly in `scope` belong to. Top-level definitions have none.
+fn parent_node(scope: &Scope, graph: &CallGraph) -> Option<NodeId> {
+    if scope.is_module() {
+        None
+    } else {
+        graph.find(&scope.name)
+    }
+}
+
+fn build_graph_from_function(body: Vec<Stmt>, scope: &Scope, graph: &mut CallGraph) {
+    for stmt in body {
+        build_graph_from_stmt(stmt, scope, Context::default(), graph);
+    }
+}
+
+/// Walks a class definition found in `scope`. Methods see the names of `scope`, not the
+/// names of the class body, just like in Python.
+fn build_graph_from_class(
+    class: StmtClassDef,
+    name: String,
+    parent: Option<NodeId>,
+    scope: &Scope,
+    context: Context,
+    graph: &mut CallGraph,
+) {
+    let class_id = graph.define(
+        &name,
+        NodeKind::Class,
+        scope.module.location(class.range),
+        parent,
+    );
+    let class_info = Class { name };
+
+    build_graph_from_decorators(class.decorator_list, class_id, scope, context, graph);
+    for base in class.bases {
+        build_graph(base, scope, context, graph);
+    }
+    for keyword in class.keywords {
+        build_graph(keyword.value, scope, context, graph);
+    }
+    // Code in the class body itself runs when the class is defined, so it belongs to the
+    // enclosing scope
+    let body_scope = Scope::class_body(scope, &class_info, class_id);
+    for stmt in class.body {
+        build_graph_from_stmt(stmt, &body_scope, context, graph);
+    }
+}
+
+fn build_graph_from_stmt(stmt: Stmt, scope: &Scope, context: Context, graph: &mut CallGraph) {
+    match stmt {
+        Stmt::Expr(StmtExpr { value, .. }) => {
+            build_graph(*value, scope, context, graph);
+        }
+        Stmt::Return(StmtReturn {
+            value: Some(value), ..
+        }) => {
+            build_graph(*value, scope, context, graph);
         }
         Stmt::Assert(StmtAssert { test, msg, .. }) => {
-            build_graph(*test, func_name.to_string(), graph);
+            build_graph(*test, scope, context, graph);
             if let Some(msg) = msg {
-                build_graph(*msg, func_name.to_string(), graph);
+                build_graph(*msg, scope, context, graph);
             }
         }
         Stmt::Try(StmtTry {
             body,
+            handlers,
+            orelse,
+            finalbody,
+            ..
+        })
+        | Stmt::TryStar(StmtTryStar {
+            body,
+            handlers,
             orelse,
             finalbody,
             ..
         }) => {
             for stmt in body {
-                build_graph_from_stmt(stmt, func_name.clone(), graph);
+                build_graph_from_stmt(stmt, scope, context, graph);
             }
+            // Exception types are only evaluated once something was raised
+            let handler_context = Context {
+                in_except: true,
+                ..context
+            };
+            for ExceptHandler::ExceptHandler(ExceptHandlerExceptHandler { type_, body, .. }) in
+                handlers
+            {
+                if let Some(type_) = type_ {
+                    build_graph(*type_, scope, handler_context, graph);
+                }
+                for stmt in body {
+                    build_graph_from_stmt(stmt, scope, handler_context, graph);
+                }
+            }
+            let else_context = Context {
+                conditional: true,
+                ..context
+            };
             for stmt in orelse {
-                build_graph_from_stmt(stmt, func_name.clone(), graph);
+                build_graph_from_stmt(stmt, scope, else_context, graph);
             }
+            let finally_context = Context {
+                in_finally: true,
+                ..context
+            };
             for stmt in finalbody {
-                build_graph_from_stmt(stmt, func_name.clone(), graph);
+                build_graph_from_stmt(stmt, scope, finally_context, graph);
             }
         }
         Stmt::With(StmtWith { items, body, .. }) => {
-            for item in items {
-                build_graph(item.context_expr, func_name.clone(), graph);
-                if let Some(item) = item.optional_vars {
-                    build_graph(*item, func_name.clone(), graph);
-                }
-            }
-            for stmt in body {
-                build_graph_from_stmt(stmt, func_name.clone(), graph);
-            }
+            build_graph_from_with(
+                items,
+                body,
+                ["__enter__", "__exit__"],
+                scope,
+                context,
+                graph,
+            );
         }
-        Stmt::For(StmtFor {
-            target, iter, body, ..
+        Stmt::AsyncWith(StmtAsyncWith { items, body, .. }) => {
+            build_graph_from_with(
+                items,
+                body,
+                ["__aenter__", "__aexit__"],
+                scope,
+                context,
+                graph,
+            );
+        }
+        Stmt::For(for_) => {
+            build_graph_from_for(for_, "__iter__", scope, context, graph);
+        }
+        Stmt::AsyncFor(StmtAsyncFor {
+            target,
+            iter,
+            body,
+            orelse,
+            type_comment,
+            range,
         }) => {
-            build_graph(*target, func_name.clone(), graph);
-            build_graph(*iter, func_name.clone(), graph);
-            for stmt in body {
-                build_graph_from_stmt(stmt, func_name.clone(), graph);
-            }
+            let for_ = StmtFor {
+                target,
+                iter,
+                body,
+                orelse,
+                type_comment,
+                range,
+            };
+            build_graph_from_for(for_, "__aiter__", scope, context, graph);
         }
         Stmt::Assign(StmtAssign { targets, value, .. }) => {
             for target in targets {
-                build_graph(target, func_name.clone(), graph);
+                build_graph(target, scope, context, graph);
             }
-            build_graph(*value, func_name.clone(), graph);
+            build_graph(*value, scope, context, graph);
         }
-        Stmt::AnnAssign(StmtAnnAssign { target, value, .. }) => {
-            build_graph(*target, func_name.clone(), graph);
+        Stmt::AnnAssign(StmtAnnAssign {
+            target,
+            annotation,
+            value,
+            ..
+        }) => {
+            build_graph(*target, scope, context, graph);
+            build_graph(*annotation, scope, context, graph);
             if let Some(value) = value {
-                build_graph(*value, func_name.clone(), graph);
+                build_graph(*value, scope, context, graph);
             };
         }
         Stmt::Delete(StmtDelete { targets, .. }) => {
             for target in targets {
-                build_graph(target, func_name.clone(), graph);
+                build_graph(target, scope, context, graph);
             }
         }
-        Stmt::While(StmtWhile { test, body, .. }) => {
-            build_graph(*test, func_name.clone(), graph);
+        Stmt::While(StmtWhile {
+            test, body, orelse, ..
+        }) => {
+            let loop_context = Context {
+                in_loop: true,
+                ..context
+            };
+            build_graph(*test, scope, loop_context, graph);
+            let body_context = Context {
+                conditional: true,
+                ..loop_context
+            };
             for stmt in body {
-                build_graph_from_stmt(stmt, func_name.clone(), graph);
+                build_graph_from_stmt(stmt, scope, body_context, graph);
+            }
+            let else_context = Context {
+                conditional: true,
+                ..context
+            };
+            for stmt in orelse {
+                build_graph_from_stmt(stmt, scope, else_context, graph);
+            }
+        }
+        Stmt::If(StmtIf {
+            test, body, orelse, ..
+        }) => {
+            // `elif` chains are nested `If` statements inside `orelse`
+            build_graph(*test, scope, context, graph);
+            let branch_context = Context {
+                conditional: true,
+                ..context
+            };
+            for stmt in body {
+                build_graph_from_stmt(stmt, scope, branch_context, graph);
+            }
+            for stmt in orelse {
+                build_graph_from_stmt(stmt, scope, branch_context, graph);
+            }
+        }
+        Stmt::Match(StmtMatch { subject, cases, .. }) => {
+            build_graph(*subject, scope, context, graph);
+            // Only the first case is sure to be tried
+            let case_context = Context {
+                conditional: true,
+                ..context
+            };
+            // Patterns only hold literals and dotted names, so they can't contain calls
+            for MatchCase { guard, body, .. } in cases {
+                if let Some(guard) = guard {
+                    build_graph(*guard, scope, case_context, graph);
+                }
+                for stmt in body {
+                    build_graph_from_stmt(stmt, scope, case_context, graph);
+                }
+            }
+        }
+        Stmt::Raise(StmtRaise { exc, cause, .. }) => {
+            if let Some(exc) = exc {
+                build_graph(*exc, scope, context, graph);
+            }
+            if let Some(cause) = cause {
+                build_graph(*cause, scope, context, graph);
             }
         }
         Stmt::AugAssign(StmtAugAssign { target, value, .. }) => {
-            build_graph(*target, func_name.clone(), graph);
-            build_graph(*value, func_name.clone(), graph);
+            build_graph(*target, scope, context, graph);
+            build_graph(*value, scope, context, graph);
+        }
+        Stmt::FunctionDef(StmtFunctionDef {
+            name,
+            args,
+            body,
+            decorator_list,
+            returns,
+            range,
+            ..
+        })
+        | Stmt::AsyncFunctionDef(StmtAsyncFunctionDef {
+            name,
+            args,
+            body,
+            decorator_list,
+            returns,
+            range,
+            ..
+        }) => {
+            // Closures can keep using `self` from the method they are defined in
+            let function_scope = match scope.class_body {
+                Some((class, _)) => Scope::function(
+                    format!("{}.{name}", class.name),
+                    Some(class),
+                    scope,
+                    &args,
+                    &body,
+                ),
+                None => Scope::function(scope.qualify(&name), scope.class, scope, &args, &body),
+            };
+            let (kind, parent) = match scope.class_body {
+                Some((_, class_id)) => (NodeKind::Method, Some(class_id)),
+                None => (NodeKind::Function, parent_node(scope, graph)),
+            };
+            let location = scope.module.location(range);
+            let id = graph.define(&function_scope.name, kind, location, parent);
+            build_graph_from_def_site(decorator_list, id, *args, returns, scope, context, graph);
+            build_graph_from_function(body, &function_scope, graph);
+        }
+        Stmt::ClassDef(class) => {
+            let (name, parent) = match scope.class_body {
+                Some((outer, class_id)) => {
+                    (format!("{}.{}", outer.name, class.name), Some(class_id))
+                }
+                None => (scope.qualify(&class.name), parent_node(scope, graph)),
+            };
+            build_graph_from_class(class, name, parent, scope, context, graph);
         }
+        // What's left, e.g. `pass`, `import` or `global`, never evaluates anything
         _ => {}
     }
 }
 
-fn build_graph(expr: Expr, func_name: String, graph: &mut HashMap<String, Vec<String>>) {
+/// Walks a `with` block. `protocol` names the methods that enter and exit the context.
+fn build_graph_from_with(
+    items: Vec<WithItem>,
+    body: Vec<Stmt>,
+    protocol: [&str; 2],
+    scope: &Scope,
+    context: Context,
+    graph: &mut CallGraph,
+) {
+    for item in items {
+        let mut current = Vec::new();
+        if let Some(class) = scope.instance_type(&item.context_expr) {
+            let offset = item.context_expr.start();
+            for method in protocol {
+                current.push(scope.implicit_call(&class, method, offset));
+            }
+        }
+        get_call_idents(item.context_expr, scope, &mut current, graph);
+        if let Some(item) = item.optional_vars {
+            get_call_idents(*item, scope, &mut current, graph);
+        }
+        add_calls(current, scope, context, graph);
+    }
+    let body_context = Context {
+        in_with: true,
+        ..context
+    };
+    for stmt in body {
+        build_graph_from_stmt(stmt, scope, body_context, graph);
+    }
+}
+
+/// Walks a `for` loop. `protocol` names the method that starts iterating.
+fn build_graph_from_for(
+    StmtFor {
+        target,
+        iter,
+        body,
+        orelse,
+        ..
+    }: StmtFor,
+    protocol: &str,
+    scope: &Scope,
+    context: Context,
+    graph: &mut CallGraph,
+) {
     let mut current = Vec::new();
-    get_call_idents(expr, &mut current);
-    for ident in current {
+    if let Some(class) = scope.instance_type(&iter) {
+        current.push(scope.implicit_call(&class, protocol, iter.start()));
+    }
+    get_call_idents(*iter, scope, &mut current, graph);
+    add_calls(current, scope, context, graph);
+    // The body may run any number of times, including none
+    let body_context = Context {
+        in_loop: true,
+        conditional: true,
+        ..context
+    };
+    build_graph(*target, scope, body_context, graph);
+    for stmt in body {
+        build_graph_from_stmt(stmt, scope, body_context, graph);
+    }
+    // `else` is skipped when the loop breaks
+    let else_context = Context {
+        conditional: true,
+        ..context
+    };
+    for stmt in orelse {
+        build_graph_from_stmt(stmt, scope, else_context, graph);
+    }
+}
+
+/// Walks the parts of a `def` statement that are evaluated in the enclosing scope when it
+/// runs: decorators, default values and annotations.
+fn build_graph_from_def_site(
+    decorator_list: Vec<Expr>,
+    function: NodeId,
+    args: Arguments,
+    returns: Option<Box<Expr>>,
+    scope: &Scope,
+    context: Context,
+    graph: &mut CallGraph,
+) {
+    build_graph_from_decorators(decorator_list, function, scope, context, graph);
+    let mut current = Vec::new();
+    get_arguments_idents(args, scope, &mut current, graph);
+    if let Some(returns) = returns {
+        get_call_idents(*returns, scope, &mut current, graph);
+    }
+    add_calls(current, scope, context, graph);
+}
+
+/// Walks the decorators of a function or class. Their names are looked up in `scope`, where
+/// they are evaluated, but the calls are made from `decorated`, the definition they apply to.
+fn build_graph_from_decorators(
+    decorator_list: Vec<Expr>,
+    decorated: NodeId,
+    scope: &Scope,
+    context: Context,
+    graph: &mut CallGraph,
+) {
+    let mut current = Vec::new();
+    for decorator in decorator_list {
+        // `@decorator` calls `decorator` with the function, so it's treated as `@decorator()`.
+        // For `@factory(...)` only the factory call is known
+        let decorator = match decorator {
+            Expr::Name(_) | Expr::Attribute(_) => ExprCall {
+                range: decorator.range(),
+                func: Box::new(decorator),
+                args: Vec::new(),
+                keywords: Vec::new(),
+            },
+            Expr::Call(call) => call,
+            decorator => {
+                get_call_idents(decorator, scope, &mut current, graph);
+                continue;
+            }
+        };
+        get_call_idents_of_call(
+            decorator,
+            Some(EdgeKind::Decorator),
+            scope,
+            &mut current,
+            graph,
+        );
+    }
+    add_calls_from(decorated, current, context, graph);
+}
+
+fn build_graph(expr: Expr, scope: &Scope, context: Context, graph: &mut CallGraph) {
+    let mut current = Vec::new();
+    get_call_idents(expr, scope, &mut current, graph);
+    add_calls(current, scope, context, graph);
+}
+
+fn add_calls(current: Vec<Call>, scope: &Scope, context: Context, graph: &mut CallGraph) {
+    let caller = if scope.is_module() {
+        if !scope.module.include_scope {
+            return;
+        }
+        match graph.find(&scope.name) {
+            Some(id) => id,
+            None => {
+                let module = scope.module;
+                let location = module.location(TextRange::up_to(module.src.text_len()));
+                graph.define(&scope.name, NodeKind::Module, location, None)
+            }
+        }
+    } else {
         graph
-            .entry(func_name.clone())
-            .or_default()
-            .push(ident.clone());
-        graph.entry(ident).or_default();
+            .find(&scope.name)
+            .expect("functions are defined before their bodies are walked")
+    };
+    add_calls_from(caller, current, context, graph);
+}
+
+fn add_calls_from(caller: NodeId, current: Vec<Call>, context: Context, graph: &mut CallGraph) {
+    for mut call in current {
+        call.site.context = context;
+        // Until a definition turns up, callees are only known by where they come from
+        let kind = match call.category {
+            Category::Builtin => NodeKind::Builtin,
+            Category::Stdlib | Category::ThirdParty => NodeKind::External,
+            Category::Project => NodeKind::Function,
+        };
+        let callee = graph.add_node(&call.callee, kind, call.category);
+        if !call.candidates.is_empty() {
+            graph.ambiguities.push(Ambiguity {
+                caller,
+                call: call.callee.clone(),
+                candidates: call.candidates,
+                site: call.site.clone(),
+            });
+        }
+        graph.edges.push(Edge {
+            caller,
+            callee,
+            kind: call.kind,
+            sites: vec![call.site],
+            count: None,
+        });
     }
 }
 
-fn get_call_idents(expr: Expr, current: &mut Vec<String>) {
-    match expr {
-        Expr::Call(ExprCall { func, args, .. }) => {
-            match *func {
-                Expr::Attribute(ExprAttribute { attr, .. }) => {
-                    current.push(attr.to_string());
-                }
-                Expr::Name(name) => {
-                    current.push(name.id.to_string());
+/// Collects a call and the calls in its arguments. `kind` overrides the kind of the call
+/// itself, which is otherwise decided by how the callee is written.
+fn get_call_idents_of_call(
+    call: ExprCall,
+    kind: Option<EdgeKind>,
+    scope: &Scope,
+    current: &mut Vec<Call>,
+    graph: &mut CallGraph,
+) {
+    let ExprCall {
+        func,
+        args,
+        keywords,
+        range,
+    } = call;
+    let call = match *func {
+        Expr::Attribute(ExprAttribute { value, attr, .. }) => {
+            let receiver = receiver_chain(&value);
+            match scope.bound_method(&value, &attr) {
+                Some(callee) => Some(Call {
+                    callee,
+                    category: Category::Project,
+                    kind: EdgeKind::AttributeCall,
+                    site: scope.module.call_site(Some(receiver), range.start()),
+                    candidates: Vec::new(),
+                }),
+                None => {
+                    // Methods called on a new instance, e.g. `Parser().parse()`
+                    let class = scope.expr_class(&value);
+                    get_call_idents(*value, scope, current, graph);
+                    Some(match class {
+                        Some(class) => {
+                            scope.instance_method_call(&class, receiver, &attr, range.start())
+                        }
+                        None => scope.method_call(receiver, &attr, range.start()),
+                    })
                 }
-                _ => {}
-            }
-            for arg in args {
-                get_call_idents(arg, current);
             }
         }
+        Expr::Name(name) => Some(scope.call(&name.id, range.start())),
+        // Calls to the results of other expressions, e.g. `handlers[0]()` or `factory()()`
+        func => {
+            get_call_idents(func, scope, current, graph);
+            None
+        }
+    };
+    // Builtins like `len(rows)` call a special method of their argument
+    let special = call
+        .as_ref()
+        .filter(|call| call.category == Category::Builtin)
+        .and_then(|call| special_method(&call.callee));
+    if let Some(method) = special {
+        if let Some(class) = args.first().and_then(|arg| scope.instance_type(arg)) {
+            current.push(scope.implicit_call(&class, method, range.start()));
+        }
+    }
+    if let Some(mut call) = call {
+        call.kind = kind.unwrap_or(call.kind);
+        current.push(call);
+    }
+    // Functions passed as arguments, e.g. `map(process, xs)` or `Thread(target=worker)`, are
+    // likely to be called by the callee
+    for arg in args
+        .into_iter()
+        .chain(keywords.into_iter().map(|keyword| keyword.value))
+    {
+        current.extend(scope.reference(&arg));
+        get_call_idents(arg, scope, current, graph);
+    }
+}
+
+fn get_call_idents(expr: Expr, scope: &Scope, current: &mut Vec<Call>, graph: &mut CallGraph) {
+    match expr {
+        Expr::Call(call) => get_call_idents_of_call(call, None, scope, current, graph),
         Expr::BoolOp(ExprBoolOp { values, .. })
         | Expr::List(ExprList { elts: values, .. })
         | Expr::Set(ExprSet { elts: values, .. })
         | Expr::JoinedStr(ExprJoinedStr { values, .. })
         | Expr::Tuple(ExprTuple { elts: values, .. }) => {
             for value in values {
-                get_call_idents(value, current);
+                get_call_idents(value, scope, current, graph);
             }
         }
         Expr::Dict(ExprDict { keys, values, .. }) => {
             for value in values {
-                get_call_idents(value, current);
+                get_call_idents(value, scope, current, graph);
             }
-            for key in keys.into_iter().filter_map(|x| x) {
-                get_call_idents(key, current);
+            for key in keys.into_iter().flatten() {
+                get_call_idents(key, scope, current, graph);
             }
         }
+        Expr::Subscript(ExprSubscript {
+            value,
+            slice,
+            ctx,
+            range,
+        }) => {
+            if let Some(class) = scope.instance_type(&value) {
+                let method = match ctx {
+                    ExprContext::Load => "__getitem__",
+                    ExprContext::Store => "__setitem__",
+                    ExprContext::Del => "__delitem__",
+                };
+                current.push(scope.implicit_call(&class, method, range.start()));
+            }
+            get_call_idents(*value, scope, current, graph);
+            get_call_idents(*slice, scope, current, graph);
+        }
+        Expr::BinOp(ExprBinOp {
+            left,
+            op,
+            right,
+            range,
+        }) => {
+            if let Some(class) = scope.instance_type(&left) {
+                let method = operator_method(op);
+                current.push(scope.implicit_call(&class, method, range.start()));
+            }
+            get_call_idents(*left, scope, current, graph);
+            get_call_idents(*right, scope, current, graph);
+        }
         Expr::NamedExpr(ExprNamedExpr {
             target: left,
             value: right,
             ..
-        })
-        | Expr::BinOp(ExprBinOp { left, right, .. })
-        | Expr::DictComp(ExprDictComp {
-            key: left,
-            value: right,
-            ..
-        })
-        | Expr::Subscript(ExprSubscript {
-            value: left,
-            slice: right,
-            ..
         }) => {
-            get_call_idents(*left, current);
-            get_call_idents(*right, current);
+            get_call_idents(*left, scope, current, graph);
+            get_call_idents(*right, scope, current, graph);
         }
         Expr::UnaryOp(ExprUnaryOp { operand, .. })
-        | Expr::Lambda(ExprLambda { body: operand, .. })
         | Expr::Await(ExprAwait { value: operand, .. })
         | Expr::Yield(ExprYield {
             value: Some(operand),
             ..
         })
         | Expr::YieldFrom(ExprYieldFrom { value: operand, .. })
-        | Expr::ListComp(ExprListComp { elt: operand, .. })
-        | Expr::SetComp(ExprSetComp { elt: operand, .. })
-        | Expr::GeneratorExp(ExprGeneratorExp { elt: operand, .. })
         | Expr::Attribute(ExprAttribute { value: operand, .. })
         | Expr::Starred(ExprStarred { value: operand, .. }) => {
-            get_call_idents(*operand, current);
+            get_call_idents(*operand, scope, current, graph);
+        }
+        Expr::Lambda(ExprLambda { args, body, range }) => {
+            let (line, column) = scope.module.locate(range.start());
+            let mut name = scope.qualify(&format!("<lambda@L{line}>"));
+            if graph.find(&name).is_some() {
+                name = scope.qualify(&format!("<lambda@L{line}:{column}>"));
+            }
+            let parent = parent_node(scope, graph);
+            graph.define(
+                &name,
+                NodeKind::Lambda,
+                scope.module.location(range),
+                parent,
+            );
+            current.push(Call {
+                callee: name.clone(),
+                category: Category::Project,
+                kind: EdgeKind::Reference,
+                site: scope.module.call_site(None, range.start()),
+                candidates: Vec::new(),
+            });
+            let lambda_scope = Scope::function(name, scope.class, scope, &args, &[]);
+            // Defaults are evaluated where the lambda is defined, the body whenever it's called
+            get_arguments_idents(*args, scope, current, graph);
+            build_graph(*body, &lambda_scope, Context::default(), graph);
+        }
+        Expr::ListComp(ExprListComp {
+            elt, generators, ..
+        })
+        | Expr::SetComp(ExprSetComp {
+            elt, generators, ..
+        })
+        | Expr::GeneratorExp(ExprGeneratorExp {
+            elt, generators, ..
+        }) => {
+            get_comprehension_idents(generators, scope, current, graph);
+            get_call_idents(*elt, scope, current, graph);
+        }
+        Expr::DictComp(ExprDictComp {
+            key,
+            value,
+            generators,
+            ..
+        }) => {
+            get_comprehension_idents(generators, scope, current, graph);
+            get_call_idents(*key, scope, current, graph);
+            get_call_idents(*value, scope, current, graph);
         }
         Expr::IfExp(ExprIfExp {
             test, body, orelse, ..
         }) => {
-            get_call_idents(*test, current);
-            get_call_idents(*body, current);
-            get_call_idents(*orelse, current);
+            get_call_idents(*test, scope, current, graph);
+            get_call_idents(*body, scope, current, graph);
+            get_call_idents(*orelse, scope, current, graph);
         }
         Expr::Compare(ExprCompare {
             left, comparators, ..
         }) => {
-            get_call_idents(*left, current);
+            get_call_idents(*left, scope, current, graph);
             for comparator in comparators {
-                get_call_idents(comparator, current);
+                get_call_idents(comparator, scope, current, graph);
             }
         }
         Expr::FormattedValue(ExprFormattedValue {
             value, format_spec, ..
         }) => {
-            get_call_idents(*value, current);
+            get_call_idents(*value, scope, current, graph);
             if let Some(format_spec) = format_spec {
-                get_call_idents(*format_spec, current);
+                get_call_idents(*format_spec, scope, current, graph);
             }
         }
         Expr::Slice(ExprSlice {
             upper, lower, step, ..
         }) => {
             if let Some(upper) = upper {
-                get_call_idents(*upper, current);
+                get_call_idents(*upper, scope, current, graph);
             }
             if let Some(lower) = lower {
-                get_call_idents(*lower, current);
+                get_call_idents(*lower, scope, current, graph);
             }
             if let Some(step) = step {
-                get_call_idents(*step, current);
+                get_call_idents(*step, scope, current, graph);
             }
         }
         _ => {}
     }
 }
+
+/// Renders the receiver of an attribute call as a dotted chain, e.g. `self.db` or `os.path`.
+/// Parts that aren't plain names are abbreviated, so `load().save()` becomes `load().save`
+/// rather than collapsing into a bare `save`.
+fn receiver_chain(expr: &Expr) -> String {
+    match expr {
+        Expr::Name(ExprName { id, .. }) => id.to_string(),
+        Expr::Attribute(ExprAttribute { value, attr, .. }) => {
+            format!("{}.{attr}", receiver_chain(value))
+        }
+        Expr::Call(ExprCall { func, .. }) => format!("{}()", receiver_chain(func)),
+        Expr::Subscript(ExprSubscript { value, .. }) => format!("{}[]", receiver_chain(value)),
+        Expr::Constant(ExprConstant {
+            value: Constant::Str(_),
+            ..
+        })
+        | Expr::JoinedStr(_) => "str".to_owned(),
+        _ => "<expr>".to_owned(),
+    }
+}
+
+/// The special method a binary operator calls on its left operand, e.g. `__add__` for `+`.
+fn operator_method(op: Operator) -> &'static str {
+    match op {
+        Operator::Add => "__add__",
+        Operator::Sub => "__sub__",
+        Operator::Mult => "__mul__",
+        Operator::MatMult => "__matmul__",
+        Operator::Div => "__truediv__",
+        Operator::Mod => "__mod__",
+        Operator::Pow => "__pow__",
+        Operator::LShift => "__lshift__",
+        Operator::RShift => "__rshift__",
+        Operator::BitOr => "__or__",
+        Operator::BitXor => "__xor__",
+        Operator::BitAnd => "__and__",
+        Operator::FloorDiv => "__floordiv__",
+    }
+}
+
+/// The special method a builtin function calls on its first argument, e.g. `__len__` for
+/// `len()`.
+fn special_method(builtin: &str) -> Option<&'static str> {
+    Some(match builtin {
+        "abs" => "__abs__",
+        "bool" => "__bool__",
+        "hash" => "__hash__",
+        "iter" => "__iter__",
+        "len" => "__len__",
+        "next" => "__next__",
+        "repr" => "__repr__",
+        "reversed" => "__reversed__",
+        "str" => "__str__",
+        _ => return None,
+    })
+}
+
+fn get_comprehension_idents(
+    generators: Vec<Comprehension>,
+    scope: &Scope,
+    current: &mut Vec<Call>,
+    graph: &mut CallGraph,
+) {
+    for Comprehension {
+        target,
+        iter,
+        ifs,
+        is_async,
+        ..
+    } in generators
+    {
+        if let Some(class) = scope.instance_type(&iter) {
+            let method = if is_async { "__aiter__" } else { "__iter__" };
+            current.push(scope.implicit_call(&class, method, iter.start()));
+        }
+        get_call_idents(iter, scope, current, graph);
+        get_call_idents(target, scope, current, graph);
+        for condition in ifs {
+            get_call_idents(condition, scope, current, graph);
+        }
+    }
+}
+
+/// Collects calls in default values and annotations of a parameter list.
+fn get_arguments_idents(
+    args: Arguments,
+    scope: &Scope,
+    current: &mut Vec<Call>,
+    graph: &mut CallGraph,
+) {
+    let Arguments {
+        posonlyargs,
+        args,
+        vararg,
+        kwonlyargs,
+        kwarg,
+        ..
+    } = args;
+    for ArgWithDefault { def, default, .. } in posonlyargs.into_iter().chain(args).chain(kwonlyargs)
+    {
+        if let Some(annotation) = def.annotation {
+            get_call_idents(*annotation, scope, current, graph);
+        }
+        if let Some(default) = default {
+            get_call_idents(*default, scope, current, graph);
+        }
+    }
+    for arg in vararg.into_iter().chain(kwarg) {
+        if let Some(annotation) = arg.annotation {
+            get_call_idents(*annotation, scope, current, graph);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::generate_graph_from_path;
+
+    fn fixture(path: &str) -> CallGraph {
+        generate_graph_from_path(&Path::new("assets").join(path), &GraphOptions::default()).unwrap()
+    }
+
+    fn edge<'g>(graph: &'g CallGraph, caller: &str, callee: &str) -> &'g Edge {
+        graph
+            .edge(caller, callee)
+            .unwrap_or_else(|| panic!("no edge from {caller} to {callee}"))
+    }
+
+    fn context(graph: &CallGraph, caller: &str, callee: &str) -> Context {
+        edge(graph, caller, callee).sites[0].context
+    }
+
+    #[test]
+    fn statements() {
+        let graph = fixture("statements");
+        let context_of = |caller, callee| context(&graph, caller, callee);
+
+        assert!(context_of("if_stmt.branches", "if_stmt.check").is_unconditional());
+        for callee in ["if_stmt.on_true", "if_stmt.on_elif", "if_stmt.on_false"] {
+            assert!(context_of("if_stmt.branches", callee).conditional);
+        }
+
+        assert!(context_of("match_stmt.dispatch", "match_stmt.command").is_unconditional());
+        assert!(context_of("match_stmt.dispatch", "match_stmt.is_ready").conditional);
+        assert!(context_of("match_stmt.dispatch", "match_stmt.fallback").conditional);
+
+        edge(&graph, "raise_stmt.fail", "raise_stmt.make_error");
+        edge(&graph, "raise_stmt.fail", "raise_stmt.original_error");
+
+        assert!(context_of("async_stmt.consume", "async_stmt.rows").in_with);
+        let handle = context_of("async_stmt.consume", "async_stmt.handle");
+        assert!(handle.in_loop && handle.in_with);
+        assert!(context_of("async_stmt.consume", "async_stmt.done").conditional);
+
+        assert!(context_of("except_handler.run", "except_handler.error_types").in_except);
+        assert!(context_of("except_handler.run", "except_handler.recover").in_except);
+        assert!(context_of("except_handler.run", "except_handler.succeeded").conditional);
+
+        assert!(context_of("try_star.run", "try_star.recover").in_except);
+        assert!(context_of("try_star.run", "try_star.cleanup").in_finally);
+
+        edge(&graph, "scope_stmt.outer", "scope_stmt.bump");
+        edge(&graph, "scope_stmt.outer.<locals>.inner", "scope_stmt.bump");
+    }
+
+    #[test]
+    fn classes() {
+        let graph = fixture("classes.py");
+        let call = edge(
+            &graph,
+            "classes.Repository.add",
+            "classes.Repository.validate",
+        );
+        assert_eq!(call.kind, EdgeKind::AttributeCall);
+        edge(&graph, "classes.Repository.add", "classes.save");
+        edge(
+            &graph,
+            "classes.Repository.create",
+            "classes.Repository.empty",
+        );
+        edge(
+            &graph,
+            "classes.Repository.Cursor.next",
+            "classes.Repository.Cursor.peek",
+        );
+        // Same method names in another class don't mix
+        edge(&graph, "classes.Cache.add", "classes.Cache.validate");
+        assert!(graph
+            .edge("classes.Cache.add", "classes.Repository.validate")
+            .is_none());
+        let method = graph.find("classes.Repository.Cursor.peek").unwrap();
+        assert_eq!(graph.node(method).kind, NodeKind::Method);
+        assert_eq!(
+            graph.node(method).parent,
+            graph.find("classes.Repository.Cursor")
+        );
+    }
+
+    #[test]
+    fn defs_in_class_body_blocks_are_methods() {
+        let src = "\
+class A:
+    if True:
+        def m(self):
+            pass
+
+    def run(self):
+        self.m()
+";
+        let graph = generate_graph(src, "t.py").unwrap();
+        let method = graph.find("t.A.m").unwrap();
+        assert_eq!(graph.node(method).kind, NodeKind::Method);
+        assert!(graph.find("t.m").is_none());
+        edge(&graph, "t.A.run", "t.A.m");
+    }
+
+    #[test]
+    fn closures() {
+        let graph = fixture("closures.py");
+        let decorator = "closures.retry.<locals>.decorator";
+        let wrapper = "closures.retry.<locals>.decorator.<locals>.wrapper";
+        let attempt = "closures.retry.<locals>.decorator.<locals>.attempt";
+        for (name, parent) in [(decorator, "closures.retry"), (wrapper, decorator)] {
+            let node = graph.node(graph.find(name).unwrap());
+            assert_eq!(node.kind, NodeKind::Function);
+            assert_eq!(node.parent, graph.find(parent));
+        }
+        // A sibling defined later in the enclosing function
+        edge(&graph, wrapper, attempt);
+        edge(&graph, attempt, "closures.log");
+        let handler = "closures.on_event.<locals>.handler";
+        edge(&graph, handler, handler);
+        edge(
+            &graph,
+            "closures.Button.click",
+            "closures.Button.click.<locals>.notify",
+        );
+        // `self` is the one of the enclosing method
+        edge(
+            &graph,
+            "closures.Button.click.<locals>.notify",
+            "closures.Button.render",
+        );
+        for name in ["wrapper", "attempt", "handler", "notify"] {
+            assert!(graph.find(&format!("closures.{name}")).is_none());
+        }
+    }
+
+    #[test]
+    fn expressions() {
+        let graph = fixture("expressions.py");
+        // Decorators apply to the function they decorate, including the factory call and
+        // its arguments
+        for decorator in ["expressions.trace", "expressions.cached"] {
+            let call = edge(&graph, "expressions.load", decorator);
+            assert_eq!(call.kind, EdgeKind::Decorator);
+        }
+        assert_eq!(
+            edge(&graph, "expressions.load", "expressions.trace").sites[0].line,
+            37
+        );
+        edge(&graph, "expressions.load", "expressions.make_config");
+        let module = "<module expressions>";
+        assert!(graph.edge(module, "expressions.trace").is_none());
+        // Defaults and annotations run where the function is defined
+        edge(&graph, module, "expressions.default_timeout");
+        assert_eq!(edge(&graph, module, "expressions.schema").weight(), 2);
+        // Keyword arguments and comprehension clauses
+        edge(&graph, "expressions.load", "expressions.default_timeout");
+        for callee in [
+            "expressions.items",
+            "expressions.keep",
+            "expressions.transform",
+        ] {
+            edge(&graph, "expressions.pipeline", callee);
+        }
+        edge(&graph, "expressions.lookup", "expressions.items");
+    }
+
+    #[test]
+    fn receivers() {
+        let graph = fixture("receivers.py");
+        let persist = "receivers.Store.persist";
+        let join = edge(&graph, persist, "os.path.join");
+        assert_eq!(join.sites[0].receiver.as_deref(), Some("os.path"));
+        let save = edge(&graph, persist, "receivers.Store.<self.db>.save");
+        assert_eq!(save.sites[0].receiver.as_deref(), Some("self.db"));
+        edge(&graph, persist, "receivers.Store.<other>.save");
+        edge(&graph, persist, "receivers.save");
+        edge(&graph, persist, "str.join");
+        edge(&graph, persist, "receivers.Store.<load()>.keys");
+        // Receivers that come from a builtin are builtin too
+        let read = edge(&graph, "receivers.read", "open().read").callee;
+        assert_eq!(graph.node(read).category, Category::Builtin);
+        edge(&graph, "<module receivers>", "receivers.<expr>.clear");
+        assert!(graph.find("receivers.<<expr>>.clear").is_none());
+    }
+
+    #[test]
+    fn imports() {
+        let graph = fixture("shop");
+        let totals = "shop.orders.totals";
+        // `load_file`, and `load` through the package's `__init__.py`, are the same function
+        assert_eq!(edge(&graph, totals, "shop.utils.load").weight(), 2);
+        let dd = edge(&graph, totals, "collections.defaultdict");
+        assert_eq!(graph.node(dd.callee).category, Category::Stdlib);
+        let array = edge(&graph, totals, "numpy.array");
+        assert_eq!(graph.node(array.callee).category, Category::ThirdParty);
+
+        // Parameters are only known to the function they belong to
+        edge(&graph, "shop.orders.each", "shop.orders.each.<action>");
+        edge(&graph, "shop.utils.retry", "shop.utils.retry.<action>");
+        assert!(graph.find("action").is_none());
+        // Names that are never bound stay global
+        edge(&graph, "shop.orders.each", "report");
+    }
+
+    #[test]
+    fn package() {
+        let graph = fixture("shop");
+        let load = graph.find("shop.utils.load").unwrap();
+        let location = graph.node(load).location.as_ref().unwrap();
+        assert!(location.file.ends_with("shop/utils.py"));
+        // Calls into another module of the package, here through `from . import utils`
+        let totals = "shop.orders.totals";
+        let call = graph
+            .calls_from(graph.find(totals).unwrap())
+            .find(|edge| edge.callee == load && edge.kind == EdgeKind::AttributeCall)
+            .unwrap();
+        assert_eq!(call.sites[0].receiver.as_deref(), Some("utils"));
+
+        // A module on its own still knows the package it's in, and reads the re-export from
+        // disk, but the functions of the other modules aren't part of the project
+        let graph = fixture("shop/orders.py");
+        let load = edge(&graph, "shop.orders.totals", "shop.utils.load").callee;
+        assert_eq!(graph.node(load).category, Category::ThirdParty);
+        assert!(graph.node(load).location.is_none());
+    }
+
+    #[test]
+    fn categories() {
+        let graph = fixture("classes.py");
+        // `repo` has an unknown type, but the project defines an `add` method
+        let add = graph.find("classes.Repository.<repo>.add").unwrap();
+        assert_eq!(graph.node(add).category, Category::Project);
+        let append = graph
+            .find("classes.Repository.<self.records>.append")
+            .unwrap();
+        assert_eq!(graph.node(append).category, Category::Builtin);
+
+        let src = "\
+import os
+
+def main(xs):
+    print(sorted(xs, key=len))
+    os.getcwd()
+";
+        let mut graph = generate_graph(src, "t.py").unwrap();
+        graph.hide(&HashSet::from([Category::Builtin, Category::Stdlib]));
+        assert!(graph.find("print").is_none() && graph.find("os.getcwd").is_none());
+        // `len` is passed to `sorted`, not called
+        let main = graph.find("t.main").unwrap();
+        assert_eq!(graph.node(main).hidden_calls, 3);
+        assert_eq!(graph.fan_out(main), 3);
+    }
+
+    #[test]
+    fn module_scope() {
+        let graph = fixture("shop");
+        let module = graph.find("<module shop.orders>").unwrap();
+        assert_eq!(graph.node(module).kind, NodeKind::Module);
+        assert_eq!(graph.node(module).location.as_ref().unwrap().start_line, 1);
+        let load = edge(&graph, "<module shop.orders>", "shop.utils.load");
+        assert_eq!(load.sites[0].line, 25);
+        assert!(graph.find("...").is_none());
+
+        let options = GraphOptions {
+            exclude_module_scope: true,
+            ..GraphOptions::default()
+        };
+        let graph = generate_graph_from_path(Path::new("assets/shop"), &options).unwrap();
+        assert!(graph.nodes().all(|(_, node)| node.kind != NodeKind::Module));
+        edge(&graph, "shop.orders.totals", "shop.utils.load");
+    }
+
+    #[test]
+    fn locations() {
+        let graph = fixture("classes.py");
+        let add = graph.node(graph.find("classes.Repository.add").unwrap());
+        let location = add.location.as_ref().unwrap();
+        assert_eq!(location.file, Path::new("assets/classes.py"));
+        assert_eq!((location.start_line, location.end_line), (9, 12));
+        let class = graph.node(graph.find("classes.Repository").unwrap());
+        let location = class.location.as_ref().unwrap();
+        assert_eq!((location.start_line, location.end_line), (5, 32));
+        // Columns are 1-based, and point at the start of the call
+        let site = &edge(&graph, "classes.Repository.add", "classes.save").sites[0];
+        assert_eq!((site.line, site.column), (12, 9));
+        assert!(graph
+            .node(graph.find("classes.save").unwrap())
+            .location
+            .is_some());
+        let append = graph
+            .find("classes.Repository.<self.records>.append")
+            .unwrap();
+        assert!(graph.node(append).location.is_none());
+    }
+
+    #[test]
+    fn repeated_calls() {
+        let src = "\
+def main(xs):
+    load()
+    for x in xs:
+        load()
+
+def load():
+    pass
+";
+        let graph = generate_graph(src, "t.py").unwrap();
+        let main = graph.find("t.main").unwrap();
+        // One edge per caller, callee and kind
+        assert_eq!(graph.calls_from(main).count(), 1);
+        let load = edge(&graph, "t.main", "t.load");
+        assert_eq!(load.weight(), 2);
+        let lines = load.sites.iter().map(|site| site.line).collect::<Vec<_>>();
+        assert_eq!(lines, [2, 4]);
+        assert!(load.is_unconditional());
+    }
+
+    #[test]
+    fn callbacks() {
+        let graph = fixture("callbacks.py");
+        for callee in [
+            "callbacks.process",
+            "callbacks.score",
+            "callbacks.worker",
+            "callbacks.handler",
+        ] {
+            let reference = edge(&graph, "callbacks.main", callee);
+            assert_eq!(reference.kind, EdgeKind::Reference);
+        }
+        // Bound methods are references too
+        let render = edge(&graph, "callbacks.Button.mount", "callbacks.Button.render");
+        assert_eq!(render.kind, EdgeKind::Reference);
+        // Names that aren't functions, like the `LIMIT` constant or the `xs` parameter, aren't
+        assert!(graph.find("callbacks.LIMIT").is_none());
+        let main = graph.find("callbacks.main").unwrap();
+        let references = graph
+            .calls_from(main)
+            .filter(|edge| edge.kind == EdgeKind::Reference);
+        assert_eq!(references.count(), 4);
+    }
+
+    #[test]
+    fn lambdas() {
+        let graph = fixture("lambdas.py");
+        let pipeline = "lambdas.pipeline";
+        let lambda = "lambdas.pipeline.<locals>.<lambda@L10>";
+        let node = graph.node(graph.find(lambda).unwrap());
+        assert_eq!(node.kind, NodeKind::Lambda);
+        assert_eq!(node.parent, graph.find(pipeline));
+        assert_eq!(edge(&graph, pipeline, lambda).kind, EdgeKind::Reference);
+        // Calls in the body belong to the lambda
+        edge(&graph, lambda, "lambdas.parse");
+        assert!(graph.edge(pipeline, "lambdas.parse").is_none());
+        edge(&graph, "lambdas.pipeline.<locals>.<lambda@L12>", "len");
+
+        // Lambdas on the same line are told apart by their column
+        let module = "<module lambdas>";
+        edge(&graph, module, "lambdas.<lambda@L15>");
+        edge(&graph, "lambdas.<lambda@L15>", "lambdas.pipeline");
+        edge(&graph, module, "lambdas.<lambda@L15:35>");
+        edge(&graph, "lambdas.<lambda@L15:35>", "print");
+        let node = graph.node(graph.find("lambdas.<lambda@L15>").unwrap());
+        assert_eq!(node.parent, None);
+    }
+
+    #[test]
+    fn dunders() {
+        let graph = fixture("dunders.py");
+        let load = "dunders.load";
+        for constructor in ["dunders.Connection.__new__", "dunders.Connection.__init__"] {
+            assert_eq!(
+                edge(&graph, load, constructor).kind,
+                EdgeKind::Instantiation
+            );
+        }
+        assert!(graph.edge(load, "dunders.Connection").is_none());
+        // A class without constructors is instantiated directly
+        let plain = edge(&graph, load, "dunders.Plain");
+        assert_eq!(plain.kind, EdgeKind::Instantiation);
+        for method in [
+            "dunders.Connection.__enter__",
+            "dunders.Connection.__exit__",
+            "dunders.Rows.__iter__",
+            "dunders.Rows.__getitem__",
+            "dunders.Rows.__add__",
+            "dunders.Rows.__len__",
+        ] {
+            assert_eq!(edge(&graph, load, method).kind, EdgeKind::Implicit);
+        }
+        // `for` loops and comprehensions both iterate
+        assert_eq!(edge(&graph, load, "dunders.Rows.__iter__").weight(), 2);
+        // Only special methods that are defined are kept
+        assert!(graph.find("dunders.Rows.__str__").is_none());
+
+        // `cls()` instantiates the class the method belongs to
+        let graph = fixture("classes.py");
+        let new = edge(
+            &graph,
+            "classes.Repository.empty",
+            "classes.Repository.__init__",
+        );
+        assert_eq!(new.kind, EdgeKind::Instantiation);
+        assert!(graph.find("cls").is_none());
+    }
+
+    #[test]
+    fn inference() {
+        let graph = fixture("inference.py");
+        // Through annotated parameters and return types, including `Optional["Parser"]`
+        edge(&graph, "inference.run", "inference.Lexer.parse");
+        assert_eq!(
+            edge(&graph, "inference.run", "inference.Parser.parse").weight(),
+            2
+        );
+        let either = graph.find("inference.<either>.parse").unwrap();
+        assert_eq!(graph.node(either).category, Category::Project);
+
+        let mixed = graph
+            .ambiguities
+            .iter()
+            .find(|ambiguity| ambiguity.call == "inference.<mixed>.parse")
+            .unwrap();
+        assert_eq!(
+            mixed.candidates,
+            ["inference.Parser.parse", "inference.Lexer.parse"]
+        );
+    }
+
+    #[test]
+    fn inheritance() {
+        let graph = fixture("inheritance.py");
+        edge(
+            &graph,
+            "inheritance.CsvExporter.__init__",
+            "inheritance.Plugin.__init__",
+        );
+        edge(
+            &graph,
+            "inheritance.CsvExporter.export",
+            "inheritance.Exporter.export",
+        );
+        // `Logging` comes before `Plugin` in the MRO of `CsvExporter`
+        edge(
+            &graph,
+            "inheritance.CsvExporter.export",
+            "inheritance.Logging.setup",
+        );
+        let dispatch = edge(&graph, "inheritance.Plugin.run", "inheritance.Exporter.run");
+        assert_eq!(dispatch.kind, EdgeKind::Dispatch);
+        let dispatch = edge(
+            &graph,
+            "inheritance.Plugin.setup",
+            "inheritance.Logging.setup",
+        );
+        assert_eq!(dispatch.kind, EdgeKind::Dispatch);
+        let new = edge(
+            &graph,
+            "inheritance.main",
+            "inheritance.CsvExporter.__init__",
+        );
+        assert_eq!(new.kind, EdgeKind::Instantiation);
+        assert_eq!(
+            edge(&graph, "inheritance.main", "inheritance.Plugin.start").weight(),
+            2
+        );
+    }
+
+    #[test]
+    fn control_flow() {
+        let graph = fixture("control_flow.py");
+        let caller = "control_flow.broadcast";
+        assert!(context(&graph, caller, "control_flow.connect").is_unconditional());
+        let info = context(&graph, caller, "logging.info");
+        assert!(info.in_loop && info.conditional);
+        let retry = edge(&graph, caller, "control_flow.retry");
+        assert!(retry.sites[0].context.in_except);
+        // The second call is in the body of a `while` loop
+        assert!(!retry.is_error_only());
+        assert!(context(&graph, caller, "control_flow.close").in_finally);
+        let send = edge(&graph, caller, "control_flow.send");
+        assert!(send.sites.iter().any(|site| site.context.in_with));
+        let enter = edge(&graph, caller, "control_flow.Session.__enter__");
+        assert_eq!(enter.kind, EdgeKind::Implicit);
+        edge(&graph, caller, "control_flow.Session.__exit__");
+    }
+}
@@ -0,0 +1,167 @@
+use rustpython_ast::{Alias, Stmt, StmtImport, StmtImportFrom};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// How many re-exports are followed before giving up, in case packages import each other
+const MAX_REEXPORT_DEPTH: usize = 16;
+
+/// Maps the names bound by `import` statements in a module to the fully qualified names they
+/// refer to, e.g. `np` → `numpy` or `load` → `pkg.utils.load`.
+#[derive(Debug, Default, Clone)]
+pub struct ImportTable {
+    names: HashMap<String, String>,
+}
+
+impl ImportTable {
+    /// Records the names bound by an `import` or `from ... import` statement. `module` is the
+    /// dotted name of the importing module, and `is_package` is set for `__init__.py` files,
+    /// which changes where relative imports start from.
+    pub fn add(&mut self, stmt: &Stmt, module: &str, is_package: bool) {
+        match stmt {
+            Stmt::Import(StmtImport { names, .. }) => {
+                for Alias { name, asname, .. } in names {
+                    match asname {
+                        // `import a.b as c` binds `c` to `a.b`
+                        Some(asname) => self.insert(asname.to_string(), name.to_string()),
+                        // `import a.b` only binds `a`
+                        None => {
+                            let top = name.split('.').next().unwrap_or(name);
+                            self.insert(top.to_owned(), top.to_owned());
+                        }
+                    }
+                }
+            }
+            Stmt::ImportFrom(StmtImportFrom {
+                module: from,
+                names,
+                level,
+                ..
+            }) => {
+                let level = level.as_ref().map_or(0, |level| level.to_usize());
+                let base = resolve_relative(module, is_package, level, from.as_deref());
+                for Alias { name, asname, .. } in names {
+                    // Star imports can't be tracked without knowing the other module's names
+                    if name.as_str() == "*" {
+                        continue;
+                    }
+                    let local = asname.as_ref().unwrap_or(name).to_string();
+                    let target = if base.is_empty() {
+                        name.to_string()
+                    } else {
+                        format!("{base}.{name}")
+                    };
+                    self.insert(local, target);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The first binding of a name wins, so `try: import ujson as json` / `except: import json`
+    /// resolves to the preferred import.
+    fn insert(&mut self, local: String, target: String) {
+        self.names.entry(local).or_insert(target);
+    }
+
+    /// Qualifies a dotted name whose first part was imported, e.g. `np.linalg.norm` →
+    /// `numpy.linalg.norm`. Returns `None` for names that weren't imported.
+    pub fn resolve(&self, dotted: &str) -> Option<String> {
+        let (first, rest) = match dotted.split_once('.') {
+            Some((first, rest)) => (first, Some(rest)),
+            None => (dotted, None),
+        };
+        let target = self.names.get(first)?;
+        Some(match rest {
+            Some(rest) => format!("{target}.{rest}"),
+            None => target.clone(),
+        })
+    }
+
+    /// Rewrites targets that point at names re-exported by a package's `__init__.py` to where
+    /// they are actually defined. `package_imports` looks up the import table of a package by
+    /// its dotted name.
+    pub fn follow_reexports(&mut self, mut package_imports: impl FnMut(&str) -> Option<Self>) {
+        for target in self.names.values_mut() {
+            for _ in 0..MAX_REEXPORT_DEPTH {
+                let Some((package, name)) = target.rsplit_once('.') else {
+                    break;
+                };
+                let Some(reexport) =
+                    package_imports(package).and_then(|imports| imports.names.get(name).cloned())
+                else {
+                    break;
+                };
+                if reexport == *target {
+                    break;
+                }
+                *target = reexport;
+            }
+        }
+    }
+}
+
+/// Finds where a `from` import starts. Each leading dot climbs one package up from the
+/// importing module.
+fn resolve_relative(module: &str, is_package: bool, level: usize, from: Option<&str>) -> String {
+    let mut parts = if level == 0 {
+        Vec::new()
+    } else {
+        let parts = module.split('.').collect::<Vec<_>>();
+        // A package's `__init__.py` is its own starting point, modules start at their parent
+        let up = level - usize::from(is_package);
+        parts[..parts.len().saturating_sub(up)].to_vec()
+    };
+    if let Some(from) = from {
+        parts.push(from);
+    }
+    parts.join(".")
+}
+
+/// Finds the dotted module name of a Python file by walking up through package directories
+/// (those that have an `__init__.py`). Also returns the directory the top-level package is in,
+/// which other modules of the project can be found relative to.
+pub fn module_name(path: &Path) -> (String, PathBuf) {
+    let mut parts = Vec::new();
+    if let Some(stem) = path.file_stem() {
+        if stem != "__init__" {
+            parts.push(stem.to_string_lossy().into_owned());
+        }
+    }
+    let mut dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    while dir.join("__init__.py").is_file() {
+        let Some(name) = dir.file_name() else {
+            break;
+        };
+        parts.push(name.to_string_lossy().into_owned());
+        dir.pop();
+    }
+    parts.reverse();
+    (parts.join("."), dir)
+}
+
+/// Like [`module_name`], but directories between `base` and the top-level package are
+/// treated as namespace packages.
+pub fn qualified_module_name(path: &Path, base: Option<&Path>) -> (String, PathBuf) {
+    let (mut name, root) = module_name(path);
+    if let Some(namespace) = base.and_then(|base| root.strip_prefix(base).ok()) {
+        let mut parts = namespace
+            .iter()
+            .map(|part| part.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        if !parts.is_empty() {
+            parts.push(name);
+            name = parts.join(".");
+        }
+    }
+    (name, root)
+}
+
+/// Where the `__init__.py` of a package would be, relative to the directory returned by
+/// [`module_name`].
+pub fn package_init(root: &Path, package: &str) -> PathBuf {
+    let mut path = root.to_path_buf();
+    path.extend(package.split('.'));
+    path.join("__init__.py")
+}
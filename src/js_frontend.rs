@@ -0,0 +1,2342 @@
+use logos::{Lexer, Logos};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    call_graph::{
+        Ambiguity, CallGraph, CallSite, Category, Context, Edge, EdgeKind, Location, NodeKind,
+    },
+    error::GraphError,
+    frontend::{Frontend, GraphOptions},
+};
+
+/// Globals that browsers and Node provide. Sorted so they can be binary searched.
+const GLOBALS: &[&str] = &[
+    "Array",
+    "Boolean",
+    "Date",
+    "Error",
+    "JSON",
+    "Map",
+    "Math",
+    "Number",
+    "Object",
+    "Promise",
+    "Proxy",
+    "Reflect",
+    "RegExp",
+    "Set",
+    "String",
+    "Symbol",
+    "TypeError",
+    "URL",
+    "URLSearchParams",
+    "WeakMap",
+    "WeakSet",
+    "alert",
+    "atob",
+    "btoa",
+    "cancelAnimationFrame",
+    "clearInterval",
+    "clearTimeout",
+    "console",
+    "decodeURIComponent",
+    "document",
+    "encodeURIComponent",
+    "fetch",
+    "globalThis",
+    "isNaN",
+    "localStorage",
+    "navigator",
+    "parseFloat",
+    "parseInt",
+    "process",
+    "queueMicrotask",
+    "requestAnimationFrame",
+    "require",
+    "sessionStorage",
+    "setInterval",
+    "setTimeout",
+    "structuredClone",
+    "window",
+];
+
+/// Methods of builtin objects, used to guess where calls on values of unknown types go
+const BUILTIN_METHODS: &[&str] = &[
+    "add",
+    "addEventListener",
+    "appendChild",
+    "assign",
+    "catch",
+    "charAt",
+    "concat",
+    "delete",
+    "endsWith",
+    "entries",
+    "error",
+    "every",
+    "filter",
+    "finally",
+    "find",
+    "findIndex",
+    "flat",
+    "flatMap",
+    "forEach",
+    "get",
+    "getElementById",
+    "has",
+    "includes",
+    "indexOf",
+    "join",
+    "json",
+    "keys",
+    "log",
+    "map",
+    "match",
+    "padStart",
+    "pop",
+    "preventDefault",
+    "push",
+    "querySelector",
+    "querySelectorAll",
+    "reduce",
+    "removeEventListener",
+    "replace",
+    "reverse",
+    "set",
+    "shift",
+    "slice",
+    "some",
+    "sort",
+    "splice",
+    "split",
+    "startsWith",
+    "stopPropagation",
+    "test",
+    "then",
+    "toLowerCase",
+    "toString",
+    "toUpperCase",
+    "trim",
+    "unshift",
+    "values",
+    "warn",
+];
+
+/// Modules that come with Node, which can also be imported with a `node:` prefix
+const NODE_MODULES: &[&str] = &[
+    "assert",
+    "buffer",
+    "child_process",
+    "crypto",
+    "events",
+    "fs",
+    "http",
+    "https",
+    "net",
+    "os",
+    "path",
+    "process",
+    "readline",
+    "stream",
+    "url",
+    "util",
+    "worker_threads",
+    "zlib",
+];
+
+/// Words that look like names to the lexer, but can't be called or passed around
+const KEYWORDS: &[&str] = &[
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "export",
+    "extends",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "of",
+    "return",
+    "static",
+    "switch",
+    "this",
+    "throw",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+/// Words in front of class members and object methods
+const MODIFIERS: &[&str] = &[
+    "abstract",
+    "async",
+    "get",
+    "override",
+    "private",
+    "protected",
+    "public",
+    "readonly",
+    "set",
+    "static",
+];
+
+/// The JavaScript and TypeScript frontend. Files aren't fully parsed: they are split into
+/// tokens, and functions, classes, imports and calls are picked out of those by their shape.
+/// Calls that can't be told apart from something else are kept rather than dropped.
+///
+/// Modules are named by their path relative to the analyzed directory, without the
+/// extension, e.g. `components/Button`, and what they define by dotted names under that,
+/// e.g. `components/Button.Button.render`.
+pub struct JavaScript;
+
+impl Frontend for JavaScript {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["js", "jsx", "mjs", "cjs", "ts", "tsx", "mts", "cts"]
+    }
+
+    fn generate(
+        &self,
+        sources: Vec<(PathBuf, String)>,
+        base: Option<&Path>,
+        options: &GraphOptions,
+    ) -> Result<CallGraph, GraphError> {
+        let outlines = sources
+            .into_iter()
+            .map(|(path, src)| {
+                let module = module_name(&path, base);
+                Scanner::new(&src, path, module).scan()
+            })
+            .collect::<Vec<_>>();
+        Ok(Resolver::new(&outlines).build_graph(options))
+    }
+}
+
+/// The path of a file relative to the analyzed directory, without its extension. An
+/// `index` file stands for its directory, like it does for imports.
+fn module_name(path: &Path, base: Option<&Path>) -> String {
+    let relative = base
+        .and_then(|base| path.strip_prefix(base).ok())
+        .unwrap_or_else(|| path.file_name().map_or(path, Path::new));
+    let name = relative
+        .with_extension("")
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    match name.strip_suffix("/index") {
+        Some(directory) => directory.to_owned(),
+        None => name,
+    }
+}
+
+#[derive(Logos, Debug, Clone, Copy, PartialEq, Eq)]
+#[logos(extras = bool)]
+#[logos(skip r"[ \t\r\n\f]+")]
+#[logos(skip r"//[^\n]*")]
+#[logos(skip r"/\*([^*]|\*+[^*/])*\*+/")]
+enum Token {
+    #[regex(r"#?[A-Za-z_$][A-Za-z0-9_$]*")]
+    Name,
+    #[regex(r"[0-9][0-9A-Za-z_.]*")]
+    Number,
+    #[token("\"", string_literal)]
+    #[token("'", string_literal)]
+    String,
+    #[regex(r"`([^`\\]|\\.)*`")]
+    Template,
+    #[token("(")]
+    OpenParen,
+    #[token(")")]
+    CloseParen,
+    #[token("{")]
+    OpenBrace,
+    #[token("}")]
+    CloseBrace,
+    #[token("[")]
+    OpenBracket,
+    #[token("]")]
+    CloseBracket,
+    #[token("=>")]
+    Arrow,
+    #[token("=")]
+    Assign,
+    #[token(":")]
+    Colon,
+    #[token(";")]
+    Semicolon,
+    #[token(",")]
+    Comma,
+    #[token(".")]
+    Dot,
+    #[token("?.")]
+    OptionalDot,
+    #[token("*")]
+    Star,
+    #[token("/", regex_literal)]
+    Slash,
+    #[regex(r"[-+%&|^!~<>?@]")]
+    #[token("...")]
+    #[token("==")]
+    #[token("===")]
+    #[token("!=")]
+    #[token("!==")]
+    #[token("<=")]
+    #[token(">=")]
+    #[token("&&")]
+    #[token("||")]
+    #[token("??")]
+    #[token("**")]
+    #[token("++")]
+    #[token("--")]
+    #[token("+=")]
+    #[token("-=")]
+    #[token("*=")]
+    #[token("/=")]
+    #[token("%=")]
+    #[token("&&=")]
+    #[token("||=")]
+    #[token("??=")]
+    Operator,
+}
+
+/// Finds the end of a string literal. Quotes that don't end on the same line, e.g. in JSX
+/// text, aren't strings and are left out rather than swallowing the line.
+fn string_literal(lexer: &mut Lexer<Token>) -> bool {
+    let quote = lexer.slice();
+    let mut escaped = false;
+    for (i, c) in lexer.remainder().char_indices() {
+        match c {
+            '\n' => return false,
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if quote.starts_with(c) => {
+                lexer.bump(i + 1);
+                return true;
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Skips the body of a regular expression literal. It starts with a `/` just like a division
+/// does, and which one it is depends on the token before it.
+fn regex_literal(lexer: &mut Lexer<Token>) {
+    if !lexer.extras {
+        return;
+    }
+    let rest = lexer.remainder();
+    let (mut in_class, mut escaped) = (false, false);
+    for (i, c) in rest.char_indices() {
+        match c {
+            '\n' => return,
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '/' if !in_class => {
+                let flags = rest[i + 1..]
+                    .chars()
+                    .take_while(char::is_ascii_alphabetic)
+                    .count();
+                lexer.bump(i + 1 + flags);
+                return;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether a `/` after the token starts a regular expression rather than a division.
+fn regex_can_follow(token: Token, text: &str) -> bool {
+    match token {
+        Token::Name => matches!(
+            text,
+            "return"
+                | "typeof"
+                | "case"
+                | "do"
+                | "else"
+                | "in"
+                | "of"
+                | "new"
+                | "delete"
+                | "void"
+                | "throw"
+                | "yield"
+                | "await"
+        ),
+        Token::Number
+        | Token::String
+        | Token::Template
+        | Token::CloseParen
+        | Token::CloseBracket
+        | Token::CloseBrace => false,
+        _ => true,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Lexeme<'s> {
+    token: Token,
+    text: &'s str,
+    /// 1-based
+    line: usize,
+    /// 1-based
+    column: usize,
+    /// Whether it's the first token on its line
+    newline: bool,
+}
+
+/// Splits a source file into tokens. Characters the lexer doesn't know, e.g. in JSX text,
+/// are left out.
+fn tokenize(src: &str) -> Vec<Lexeme<'_>> {
+    let line_starts = std::iter::once(0)
+        .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+        .collect::<Vec<_>>();
+    let line_of = |offset: usize| line_starts.partition_point(|&start| start <= offset);
+
+    let mut lexer = Token::lexer(src);
+    lexer.extras = true;
+    let mut tokens = Vec::new();
+    let mut last_line = 0;
+    while let Some(token) = lexer.next() {
+        let Ok(token) = token else {
+            continue;
+        };
+        let span = lexer.span();
+        let line = line_of(span.start);
+        tokens.push(Lexeme {
+            token,
+            text: lexer.slice(),
+            line,
+            column: span.start - line_starts[line - 1] + 1,
+            newline: line > last_line,
+        });
+        last_line = line_of(span.end.saturating_sub(1).max(span.start));
+        lexer.extras = regex_can_follow(token, lexer.slice());
+    }
+    tokens
+}
+
+fn is_open(token: Token) -> bool {
+    matches!(
+        token,
+        Token::OpenParen | Token::OpenBrace | Token::OpenBracket
+    )
+}
+
+fn is_close(token: Token) -> bool {
+    matches!(
+        token,
+        Token::CloseParen | Token::CloseBrace | Token::CloseBracket
+    )
+}
+
+fn closer(open: Token) -> Token {
+    match open {
+        Token::OpenParen => Token::CloseParen,
+        Token::OpenBracket => Token::CloseBracket,
+        _ => Token::CloseBrace,
+    }
+}
+
+/// Whether an expression goes on after the token, even if the next token is on a new line.
+fn continues_after(token: Token) -> bool {
+    !matches!(
+        token,
+        Token::Name
+            | Token::Number
+            | Token::String
+            | Token::Template
+            | Token::CloseParen
+            | Token::CloseBrace
+            | Token::CloseBracket
+            | Token::Semicolon
+    )
+}
+
+/// Whether a token at the start of a line continues the expression on the line before.
+fn continues_before(token: Token) -> bool {
+    !matches!(
+        token,
+        Token::Name | Token::Number | Token::String | Token::Template | Token::OpenBrace
+    )
+}
+
+/// A function, method or class found in a file.
+struct Definition {
+    name: String,
+    kind: NodeKind,
+    location: Location,
+    /// The function or class it's defined in
+    parent: Option<String>,
+}
+
+/// The names a function's body defines.
+#[derive(Default)]
+struct FunctionScope {
+    /// The function this one is defined in
+    parent: Option<String>,
+    /// The class `this` is an instance of
+    class: Option<String>,
+    /// Functions and classes defined in the body, with their qualified names
+    locals: HashMap<String, String>,
+    variables: HashMap<String, Variable>,
+}
+
+/// The classes a variable is bound to with `new`, as they are written. Parameters and
+/// destructured names are variables bound to something else.
+#[derive(Default, Clone)]
+struct Variable {
+    classes: Vec<Vec<String>>,
+    /// Whether it's also bound to something else
+    unknown: bool,
+}
+
+/// A binding made by `import` or `require`.
+struct Import {
+    specifier: String,
+    /// The imported name, `default` for the default export, or `None` for the whole module
+    name: Option<String>,
+}
+
+/// A class, before its base class is resolved.
+struct Class {
+    name: String,
+    /// The base class as written, e.g. `React.Component`
+    base: Option<Vec<String>>,
+    /// The function the class is defined in
+    scope: Option<String>,
+}
+
+/// What a call, or a reference to a function, goes to, as it's written.
+enum Target {
+    /// `load()`
+    Name(String),
+    /// `api.users.load()`, as the parts of the receiver and the method
+    Member(Vec<String>, String),
+    /// `new Store()`
+    New(Vec<String>),
+    /// An anonymous function, which is referred to where it's defined
+    Defined(String),
+}
+
+struct Call {
+    /// `None` for calls made by the module's top level
+    caller: Option<String>,
+    target: Target,
+    kind: EdgeKind,
+    line: usize,
+    column: usize,
+    context: Context,
+}
+
+/// What a file defines, imports and calls, before the calls are resolved.
+struct Outline {
+    path: PathBuf,
+    module: String,
+    lines: usize,
+    definitions: Vec<Definition>,
+    /// Functions and classes defined at the top level, with their qualified names
+    names: HashMap<String, String>,
+    scopes: HashMap<String, FunctionScope>,
+    /// Variables bound at the top level
+    variables: HashMap<String, Variable>,
+    imports: HashMap<String, Import>,
+    /// The name of what `export default` or `module.exports` exports
+    default: Option<String>,
+    classes: Vec<Class>,
+    calls: Vec<Call>,
+}
+
+impl Outline {
+    fn location(&self, start_line: usize, end_line: usize) -> Location {
+        Location {
+            file: self.path.clone(),
+            start_line,
+            end_line,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FrameKind {
+    Block,
+    /// An object literal, and the name it's assigned to
+    Object(Option<String>),
+    /// A class body
+    Class,
+    Paren,
+    Bracket,
+    /// A statement without braces around it, e.g. the body of `if (done) return;`
+    Statement,
+    /// The body of an arrow function that is a single expression
+    ArrowBody,
+}
+
+/// A bracketed part of a file, or a statement or expression body that ends without one.
+#[derive(Debug, Clone)]
+struct Frame {
+    kind: FrameKind,
+    /// The token that ends the frame, or `None` if it ends with its statement
+    close: Option<Token>,
+    /// The index of its first token
+    start: usize,
+    /// The innermost function, which calls are attributed to
+    function: Option<String>,
+    /// The class `this` is an instance of
+    class: Option<String>,
+    context: Context,
+    /// The index of the definition whose body the frame is, so its end can be filled in
+    definition: Option<usize>,
+}
+
+/// How a function or object literal is bound to a name, found by looking at what's in
+/// front of it.
+enum Binding {
+    /// `const load = () => {}` or `function load() {}`
+    Name(String),
+    /// `{ load: () => {} }`
+    Key(String),
+    /// `this.load = () => {}`
+    This(String),
+}
+
+/// Picks the definitions, imports and calls out of a file's tokens.
+struct Scanner<'s> {
+    tokens: Vec<Lexeme<'s>>,
+    /// The index of the bracket matching each bracket
+    matches: Vec<Option<usize>>,
+    frames: Vec<Frame>,
+    /// Bodies of control flow statements that start at a token, and their context
+    pending: Vec<(usize, Context)>,
+    outline: Outline,
+}
+
+impl<'s> Scanner<'s> {
+    fn new(src: &'s str, path: PathBuf, module: String) -> Self {
+        let tokens = tokenize(src);
+        let mut matches = vec![None; tokens.len()];
+        let mut open = Vec::new();
+        for (i, lexeme) in tokens.iter().enumerate() {
+            if is_open(lexeme.token) {
+                open.push(i);
+            } else if is_close(lexeme.token) {
+                // Unbalanced closing brackets are left unmatched
+                if let Some(&start) = open.last() {
+                    if closer(tokens[start].token) == lexeme.token {
+                        open.pop();
+                        matches[start] = Some(i);
+                        matches[i] = Some(start);
+                    }
+                }
+            }
+        }
+        let root = Frame {
+            kind: FrameKind::Block,
+            close: None,
+            start: 0,
+            function: None,
+            class: None,
+            context: Context::default(),
+            definition: None,
+        };
+        Self {
+            tokens,
+            matches,
+            frames: vec![root],
+            pending: Vec::new(),
+            outline: Outline {
+                path,
+                module,
+                lines: src.lines().count().max(1),
+                definitions: Vec::new(),
+                names: HashMap::new(),
+                scopes: HashMap::new(),
+                variables: HashMap::new(),
+                imports: HashMap::new(),
+                default: None,
+                classes: Vec::new(),
+                calls: Vec::new(),
+            },
+        }
+    }
+
+    fn scan(mut self) -> Outline {
+        let mut i = 0;
+        while i < self.tokens.len() {
+            i = self.step(i);
+        }
+        let end = self.tokens.last().map_or(1, |lexeme| lexeme.line);
+        while self.frames.len() > 1 {
+            self.pop(end);
+        }
+        self.outline
+    }
+
+    fn token(&self, i: usize) -> Option<Token> {
+        self.tokens.get(i).map(|lexeme| lexeme.token)
+    }
+
+    fn text(&self, i: usize) -> &'s str {
+        self.tokens.get(i).map_or("", |lexeme| lexeme.text)
+    }
+
+    fn frame(&self) -> &Frame {
+        self.frames.last().expect("the top level is never popped")
+    }
+
+    /// Handles the token at `i` and returns the index of the next one to handle.
+    fn step(&mut self, i: usize) -> usize {
+        if self.ends_statement(i) {
+            let line = self.tokens[i - 1].line;
+            while self.frames.len() > 1 && self.frame().close.is_none() {
+                self.pop(line);
+            }
+            if matches!(self.token(i), Some(Token::Semicolon | Token::Comma)) {
+                return i + 1;
+            }
+        }
+        // A body that starts with a token that was skipped, e.g. `while (busy);`
+        while self.pending.last().is_some_and(|&(start, _)| start < i) {
+            self.pending.pop();
+        }
+        if self.pending.last().is_some_and(|&(start, _)| start == i) {
+            let (_, context) = self.pending.pop().expect("checked above");
+            return self.open_body(i, context);
+        }
+
+        let lexeme = self.tokens[i];
+        match lexeme.token {
+            Token::OpenBrace => {
+                let kind = self.brace_kind(i);
+                let frame = self.frame();
+                self.frames.push(Frame {
+                    kind,
+                    close: Some(Token::CloseBrace),
+                    start: i + 1,
+                    definition: None,
+                    ..frame.clone()
+                });
+                i + 1
+            }
+            Token::OpenParen => match self.arrow_after(i) {
+                Some(arrow) => self.arrow(i, arrow),
+                None => {
+                    self.open(i, FrameKind::Paren);
+                    i + 1
+                }
+            },
+            Token::OpenBracket => {
+                self.open(i, FrameKind::Bracket);
+                i + 1
+            }
+            token if is_close(token) => {
+                while self.frames.len() > 1 && self.frame().close.is_none() {
+                    self.pop(lexeme.line);
+                }
+                if self.frames.len() > 1 && self.frame().close == Some(token) {
+                    self.pop(lexeme.line);
+                }
+                i + 1
+            }
+            Token::Name => self.name(i),
+            _ => i + 1,
+        }
+    }
+
+    /// Whether the statement or expression body at the top of the stack ends at `i`.
+    fn ends_statement(&self, i: usize) -> bool {
+        let frame = self.frame();
+        if self.frames.len() == 1 || frame.close.is_some() || i <= frame.start {
+            return false;
+        }
+        let lexeme = self.tokens[i];
+        match lexeme.token {
+            Token::Semicolon => true,
+            Token::Comma => frame.kind == FrameKind::ArrowBody,
+            _ => {
+                lexeme.newline
+                    && !continues_after(self.tokens[i - 1].token)
+                    && !continues_before(lexeme.token)
+            }
+        }
+    }
+
+    fn open(&mut self, i: usize, kind: FrameKind) {
+        let frame = self.frame();
+        self.frames.push(Frame {
+            kind,
+            close: Some(closer(self.tokens[i].token)),
+            start: i + 1,
+            definition: None,
+            ..frame.clone()
+        });
+    }
+
+    fn pop(&mut self, end_line: usize) {
+        let frame = self.frames.pop().expect("callers keep the top level");
+        if let Some(definition) = frame.definition {
+            self.outline.definitions[definition].location.end_line = end_line;
+        }
+    }
+
+    /// Opens the body of a control flow statement, which may or may not have braces.
+    fn open_body(&mut self, i: usize, context: Context) -> usize {
+        let frame = self.frame().clone();
+        if self.token(i) == Some(Token::OpenBrace) {
+            self.frames.push(Frame {
+                kind: FrameKind::Block,
+                close: Some(Token::CloseBrace),
+                start: i + 1,
+                context,
+                definition: None,
+                ..frame
+            });
+            return i + 1;
+        }
+        self.frames.push(Frame {
+            kind: FrameKind::Statement,
+            close: None,
+            start: i,
+            context,
+            definition: None,
+            ..frame
+        });
+        // The body's first token still needs handling
+        self.step(i)
+    }
+
+    /// Tells object literals apart from blocks by what comes before the brace.
+    fn brace_kind(&self, i: usize) -> FrameKind {
+        let Some(before) = i.checked_sub(1) else {
+            return FrameKind::Block;
+        };
+        let is_object = match self.tokens[before].token {
+            Token::Assign
+            | Token::Colon
+            | Token::OpenParen
+            | Token::Comma
+            | Token::OpenBracket
+            | Token::Operator
+            | Token::Arrow => true,
+            Token::Name => matches!(self.text(before), "return" | "default" | "yield" | "await"),
+            _ => false,
+        };
+        if !is_object {
+            return FrameKind::Block;
+        }
+        // Nested objects are named after their keys, e.g. `methods.save`
+        let name = match (self.binding(i), &self.frame().kind) {
+            (Some(Binding::Key(key)), FrameKind::Object(Some(object))) => {
+                Some(format!("{object}.{key}"))
+            }
+            (Some(Binding::Name(name) | Binding::Key(name) | Binding::This(name)), _) => Some(name),
+            (None, _) => None,
+        };
+        FrameKind::Object(name)
+    }
+
+    /// Finds how what starts at `i` is bound to a name, if it is.
+    fn binding(&self, i: usize) -> Option<Binding> {
+        let mut before = i.checked_sub(1)?;
+        if self.text(before) == "async" {
+            before = before.checked_sub(1)?;
+        }
+        match self.tokens[before].token {
+            Token::Assign => {
+                // `const handler: Handler = ...` is named `handler`, not after its type
+                for j in (before.saturating_sub(16)..before).rev() {
+                    match self.tokens[j].token {
+                        Token::Semicolon | Token::Assign | Token::OpenBrace | Token::CloseBrace => {
+                            break
+                        }
+                        Token::Name if matches!(self.text(j), "const" | "let" | "var") => {
+                            return (self.token(j + 1) == Some(Token::Name))
+                                .then(|| Binding::Name(self.text(j + 1).to_owned()));
+                        }
+                        _ => {}
+                    }
+                }
+                let name = before.checked_sub(1)?;
+                if self.token(name) != Some(Token::Name) {
+                    return None;
+                }
+                let name_text = self.text(name).to_owned();
+                let is_member = name
+                    .checked_sub(1)
+                    .is_some_and(|dot| self.token(dot) == Some(Token::Dot));
+                if is_member && self.receiver(name - 1) == ["this"] {
+                    return Some(Binding::This(name_text));
+                }
+                Some(Binding::Name(name_text))
+            }
+            Token::Colon => {
+                let key = before.checked_sub(1)?;
+                match self.tokens[key].token {
+                    Token::Name => Some(Binding::Key(self.text(key).to_owned())),
+                    Token::String => Some(Binding::Key(unquote(self.text(key)).to_owned())),
+                    _ => None,
+                }
+            }
+            Token::Name if self.text(before) == "default" => {
+                Some(Binding::Name("default".to_owned()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Renders the receiver of a member access that ends with the `.` at `dot`, as names.
+    /// Parts that aren't plain names are abbreviated, so `load().then` has the receiver
+    /// `load()`.
+    fn receiver(&self, dot: usize) -> Vec<String> {
+        let mut parts = Vec::new();
+        let mut j = dot;
+        loop {
+            let Some(end) = j.checked_sub(1) else {
+                parts.push("<expr>".to_owned());
+                break;
+            };
+            let (part, start) = match self.tokens[end].token {
+                Token::Name => (self.text(end).to_owned(), end),
+                Token::CloseParen | Token::CloseBracket => {
+                    let suffix = if self.tokens[end].token == Token::CloseParen {
+                        "()"
+                    } else {
+                        "[]"
+                    };
+                    match self.matches[end]
+                        .and_then(|open| open.checked_sub(1))
+                        .filter(|&callee| self.token(callee) == Some(Token::Name))
+                    {
+                        Some(callee) => (format!("{}{suffix}", self.text(callee)), callee),
+                        None => {
+                            parts.push("<expr>".to_owned());
+                            break;
+                        }
+                    }
+                }
+                Token::String | Token::Template | Token::Number => {
+                    parts.push("<literal>".to_owned());
+                    break;
+                }
+                _ => {
+                    parts.push("<expr>".to_owned());
+                    break;
+                }
+            };
+            parts.push(part);
+            match start.checked_sub(1) {
+                Some(dot) if matches!(self.token(dot), Some(Token::Dot | Token::OptionalDot)) => {
+                    j = dot;
+                }
+                _ => break,
+            }
+        }
+        parts.reverse();
+        parts
+    }
+
+    /// If the parenthesis at `i` opens the parameters of an arrow function, finds its `=>`.
+    fn arrow_after(&self, i: usize) -> Option<usize> {
+        let close = self.matches[i]?;
+        match self.token(close + 1)? {
+            Token::Arrow => Some(close + 1),
+            // A return type, e.g. `(id: string): Promise<User> => ...`
+            Token::Colon => {
+                let mut j = close + 2;
+                while j < self.tokens.len().min(close + 40) {
+                    match self.tokens[j].token {
+                        Token::Arrow => return Some(j),
+                        token if is_open(token) => j = self.matches[j]?,
+                        Token::Semicolon | Token::Assign | Token::Comma => return None,
+                        token if is_close(token) => return None,
+                        _ => {}
+                    }
+                    j += 1;
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the function starting at `i` is really a type, e.g. the type of a parameter in
+    /// `(onSave: (user: User) => void)`.
+    fn is_function_type(&self, i: usize) -> bool {
+        let mut before = i.checked_sub(1);
+        if before.is_some_and(|j| self.text(j) == "async") {
+            before = before.and_then(|j| j.checked_sub(1));
+        }
+        before.is_some_and(|j| self.token(j) == Some(Token::Colon))
+            && !matches!(self.frame().kind, FrameKind::Object(_))
+    }
+
+    /// Handles an arrow function whose parameters start at `i`, and returns the index of the
+    /// first token of its body.
+    fn arrow(&mut self, i: usize, arrow: usize) -> usize {
+        if self.is_function_type(i) {
+            return arrow + 1;
+        }
+        let start = if i > 0 && self.text(i - 1) == "async" {
+            i - 1
+        } else {
+            i
+        };
+        let binding = self.binding(start);
+        let definition = self.define_function(binding, "arrow", i, NodeKind::Function);
+        let close = self.matches[i].unwrap_or(arrow);
+        self.open_function(arrow + 1, definition, i + 1..close)
+    }
+
+    /// Opens the body of a function that starts at `i`, which is either a block or, for arrow
+    /// functions, a single expression. `parameters` are the tokens of its parameter list.
+    fn open_function(&mut self, i: usize, definition: usize, parameters: Range<usize>) -> usize {
+        let name = self.outline.definitions[definition].name.clone();
+        let class = self.outline.scopes[&name].class.clone();
+        for parameter in self.pattern_names(parameters) {
+            self.variables(Some(&name))
+                .entry(parameter)
+                .or_default()
+                .unknown = true;
+        }
+        let brace = self.token(i) == Some(Token::OpenBrace);
+        self.frames.push(Frame {
+            kind: if brace {
+                FrameKind::Block
+            } else {
+                FrameKind::ArrowBody
+            },
+            close: brace.then_some(Token::CloseBrace),
+            start: if brace { i + 1 } else { i },
+            function: Some(name),
+            class,
+            context: Context::default(),
+            definition: Some(definition),
+        });
+        if brace {
+            i + 1
+        } else {
+            i
+        }
+    }
+
+    /// Adds a function to the outline, named by how it's bound. `word` names anonymous ones,
+    /// e.g. `<arrow@L12>`, which are referred to where they are defined.
+    fn define_function(
+        &mut self,
+        binding: Option<Binding>,
+        word: &str,
+        i: usize,
+        kind: NodeKind,
+    ) -> usize {
+        let frame = self.frame().clone();
+        let lexeme = self.tokens[i];
+        let scope = frame.function.as_deref().unwrap_or(&self.outline.module);
+        let (name, kind, parent, local) = match (binding, &frame.kind) {
+            (Some(Binding::Name(name) | Binding::This(name)), FrameKind::Class) => {
+                let class = frame.class.clone().unwrap_or_default();
+                (
+                    format!("{class}.{name}"),
+                    NodeKind::Method,
+                    Some(class),
+                    None,
+                )
+            }
+            (Some(Binding::This(name)), _) if frame.class.is_some() => {
+                let class = frame.class.clone().unwrap_or_default();
+                (
+                    format!("{class}.{name}"),
+                    NodeKind::Method,
+                    Some(class),
+                    None,
+                )
+            }
+            (Some(Binding::Name(name) | Binding::This(name)), _) => {
+                let qualified = format!("{scope}.{name}");
+                (qualified, kind, frame.function.clone(), Some(name))
+            }
+            (Some(Binding::Key(key)), FrameKind::Object(Some(object))) => {
+                let qualified = format!("{scope}.{object}.{key}");
+                (qualified, kind, frame.function.clone(), None)
+            }
+            (Some(Binding::Key(key)), _) => {
+                (format!("{scope}.{key}"), kind, frame.function.clone(), None)
+            }
+            (None, _) => {
+                let mut name = format!("{scope}.<{word}@L{}>", lexeme.line);
+                if self.outline.scopes.contains_key(&name) {
+                    name = format!("{scope}.<{word}@L{}:{}>", lexeme.line, lexeme.column);
+                }
+                self.add_call(Target::Defined(name.clone()), EdgeKind::Reference, i);
+                (name, NodeKind::Lambda, frame.function.clone(), None)
+            }
+        };
+        if let Some(local) = local {
+            self.bind_local(&frame, local, name.clone());
+        }
+        let class = match kind {
+            NodeKind::Method => parent.clone(),
+            _ => frame.class.clone(),
+        };
+        self.outline.scopes.insert(
+            name.clone(),
+            FunctionScope {
+                parent: frame.function.clone(),
+                class,
+                ..FunctionScope::default()
+            },
+        );
+        let location = self.outline.location(lexeme.line, lexeme.line);
+        self.outline.definitions.push(Definition {
+            name,
+            kind,
+            location,
+            parent,
+        });
+        self.outline.definitions.len() - 1
+    }
+
+    /// Makes `name` refer to a definition in the function the frame is in, or at the top
+    /// level.
+    fn bind_local(&mut self, frame: &Frame, name: String, qualified: String) {
+        let locals = match &frame.function {
+            Some(function) => {
+                &mut self
+                    .outline
+                    .scopes
+                    .get_mut(function)
+                    .expect("functions get a scope when they are defined")
+                    .locals
+            }
+            None => &mut self.outline.names,
+        };
+        locals.insert(name, qualified);
+    }
+
+    fn add_call(&mut self, target: Target, kind: EdgeKind, i: usize) {
+        let frame = self.frame();
+        let lexeme = self.tokens[i];
+        self.outline.calls.push(Call {
+            caller: frame.function.clone(),
+            target,
+            kind,
+            line: lexeme.line,
+            column: lexeme.column,
+            context: frame.context,
+        });
+    }
+
+    /// Handles a name, which may be a keyword, a definition, a call or a reference.
+    fn name(&mut self, i: usize) -> usize {
+        let text = self.text(i);
+        let next = self.token(i + 1);
+        let after_dot = i > 0 && matches!(self.token(i - 1), Some(Token::Dot | Token::OptionalDot));
+        if !after_dot {
+            match text {
+                "function" => return self.function(i),
+                "class" => return self.class(i),
+                "import" if next != Some(Token::OpenParen) && next != Some(Token::Dot) => {
+                    return self.import(i)
+                }
+                "export" => return self.export(i),
+                "if" | "switch" | "while" | "for" | "catch" | "else" | "do" | "finally" => {
+                    return self.control(i)
+                }
+                "new" => return self.construct(i),
+                "const" | "let" | "var" => return self.declaration(i),
+                "require" if next == Some(Token::OpenParen) => self.require(i),
+                "module"
+                    if self.text(i + 2) == "exports"
+                        && self.token(i + 3) == Some(Token::Assign) =>
+                {
+                    self.set_default(i + 4)
+                }
+                "interface" | "enum" | "declare" if next == Some(Token::Name) => {
+                    return self.skip_statement(i)
+                }
+                "type"
+                    if next == Some(Token::Name)
+                        && matches!(self.token(i + 2), Some(Token::Assign | Token::Operator)) =>
+                {
+                    return self.skip_statement(i)
+                }
+                _ => {}
+            }
+        }
+
+        // An arrow function with a single parameter, e.g. `user => user.id`
+        if next == Some(Token::Arrow) && !after_dot {
+            if self.is_function_type(i) {
+                return i + 2;
+            }
+            let binding = self.binding(i);
+            let definition = self.define_function(binding, "arrow", i, NodeKind::Function);
+            return self.open_function(i + 2, definition, i..i + 1);
+        }
+
+        let calls = next == Some(Token::OpenParen)
+            || (next == Some(Token::OptionalDot) && self.token(i + 2) == Some(Token::OpenParen));
+        if calls {
+            if !after_dot && self.is_method_definition(i) {
+                return self.method(i);
+            }
+            if after_dot {
+                let receiver = self.receiver(i - 1);
+                let target = Target::Member(receiver, text.to_owned());
+                self.add_call(target, EdgeKind::AttributeCall, i);
+            } else if text == "super" {
+                let target = Target::Member(vec!["super".to_owned()], "constructor".to_owned());
+                self.add_call(target, EdgeKind::Call, i);
+            } else if KEYWORDS.binary_search(&text).is_err() {
+                self.add_call(Target::Name(text.to_owned()), EdgeKind::Call, i);
+            }
+            return i + 1;
+        }
+
+        // Functions passed around without being called, e.g. `onClick={this.save}`. Only the
+        // ones that turn out to be defined are kept
+        let is_used = !matches!(
+            next,
+            Some(Token::Dot | Token::OptionalDot | Token::Assign | Token::Colon)
+        );
+        if is_used && after_dot && self.receiver(i - 1) == ["this"] {
+            let target = Target::Member(vec!["this".to_owned()], text.to_owned());
+            self.add_call(target, EdgeKind::Reference, i);
+        } else if is_used && !after_dot && KEYWORDS.binary_search(&text).is_err() {
+            self.add_call(Target::Name(text.to_owned()), EdgeKind::Reference, i);
+        }
+        i + 1
+    }
+
+    /// Whether the name at `i`, followed by parentheses, starts a method of a class or an
+    /// object literal rather than a call.
+    fn is_method_definition(&self, i: usize) -> bool {
+        if !matches!(self.frame().kind, FrameKind::Class | FrameKind::Object(_)) {
+            return false;
+        }
+        let Some(close) = self.matches[i + 1] else {
+            return false;
+        };
+        if !matches!(self.token(close + 1), Some(Token::OpenBrace | Token::Colon)) {
+            return false;
+        }
+        let Some(before) = i.checked_sub(1) else {
+            return true;
+        };
+        self.tokens[i].newline
+            || matches!(
+                self.tokens[before].token,
+                Token::OpenBrace
+                    | Token::Comma
+                    | Token::Semicolon
+                    | Token::CloseBrace
+                    | Token::Star
+            )
+            || MODIFIERS.contains(&self.text(before))
+    }
+
+    /// Handles a method, or a method signature without a body.
+    fn method(&mut self, i: usize) -> usize {
+        let close = self.matches[i + 1].expect("checked by is_method_definition");
+        let Some(open) = self.body_after(close) else {
+            return close + 1;
+        };
+        let name = self.text(i).to_owned();
+        let binding = match self.frame().kind {
+            FrameKind::Class => Binding::Name(name),
+            _ => Binding::Key(name),
+        };
+        let definition = self.define_function(Some(binding), "method", i, NodeKind::Function);
+        self.open_function(open, definition, i + 2..close)
+    }
+
+    /// Finds the brace that starts a function's body after its parameters, skipping a return
+    /// type. Returns `None` for signatures without a body.
+    fn body_after(&self, close: usize) -> Option<usize> {
+        let mut j = close + 1;
+        while j < self.tokens.len() {
+            match self.tokens[j].token {
+                Token::OpenBrace => return Some(j),
+                Token::OpenParen | Token::OpenBracket => j = self.matches[j]?,
+                Token::Semicolon | Token::Comma | Token::Assign => return None,
+                token if is_close(token) => return None,
+                Token::Name if matches!(self.text(j), "function" | "export" | "declare") => {
+                    return None
+                }
+                _ => {}
+            }
+            j += 1;
+        }
+        None
+    }
+
+    /// Handles a function declaration or expression.
+    fn function(&mut self, i: usize) -> usize {
+        let mut j = i + 1;
+        if self.token(j) == Some(Token::Star) {
+            j += 1;
+        }
+        let declared = (self.token(j) == Some(Token::Name)).then(|| self.text(j).to_owned());
+        // Type parameters, e.g. `function first<T>(items: T[])`
+        while j < self.tokens.len() && self.tokens[j].token != Token::OpenParen {
+            if matches!(
+                self.tokens[j].token,
+                Token::Semicolon | Token::OpenBrace | Token::CloseBrace
+            ) {
+                return i + 1;
+            }
+            j += 1;
+        }
+        let Some(close) = self.matches.get(j).copied().flatten() else {
+            return i + 1;
+        };
+        let Some(open) = self.body_after(close) else {
+            // An overload signature
+            return close + 1;
+        };
+        let start = if i > 0 && self.text(i - 1) == "async" {
+            i - 1
+        } else {
+            i
+        };
+        let binding = declared.map(Binding::Name).or_else(|| self.binding(start));
+        let definition = self.define_function(binding, "function", i, NodeKind::Function);
+        self.open_function(open, definition, j + 1..close)
+    }
+
+    /// Handles a class declaration or expression.
+    fn class(&mut self, i: usize) -> usize {
+        let mut j = i + 1;
+        let declared = (self.token(j) == Some(Token::Name)
+            && !matches!(self.text(j), "extends" | "implements"))
+        .then(|| self.text(j).to_owned());
+        let mut base = None;
+        while j < self.tokens.len() && self.tokens[j].token != Token::OpenBrace {
+            match self.tokens[j].token {
+                Token::Semicolon => return i + 1,
+                Token::Name if self.text(j) == "extends" && base.is_none() => {
+                    let mut parts = Vec::new();
+                    let mut k = j + 1;
+                    while self.token(k) == Some(Token::Name) {
+                        parts.push(self.text(k).to_owned());
+                        if self.token(k + 1) != Some(Token::Dot) {
+                            break;
+                        }
+                        k += 2;
+                    }
+                    base = Some(parts);
+                }
+                _ => {}
+            }
+            j += 1;
+        }
+        if j >= self.tokens.len() {
+            return i + 1;
+        }
+
+        let frame = self.frame().clone();
+        let lexeme = self.tokens[i];
+        let scope = frame.function.as_deref().unwrap_or(&self.outline.module);
+        let local = declared.or_else(|| match self.binding(i) {
+            Some(Binding::Name(name)) => Some(name),
+            _ => None,
+        });
+        let name = match &local {
+            Some(local) => format!("{scope}.{local}"),
+            None => format!("{scope}.<class@L{}>", lexeme.line),
+        };
+        if let Some(local) = local {
+            self.bind_local(&frame, local, name.clone());
+        }
+        self.outline.classes.push(Class {
+            name: name.clone(),
+            base: base.filter(|parts| !parts.is_empty()),
+            scope: frame.function.clone(),
+        });
+        let location = self.outline.location(lexeme.line, lexeme.line);
+        self.outline.definitions.push(Definition {
+            name: name.clone(),
+            kind: NodeKind::Class,
+            location,
+            parent: frame.function.clone(),
+        });
+        self.frames.push(Frame {
+            kind: FrameKind::Class,
+            close: Some(Token::CloseBrace),
+            start: j + 1,
+            class: Some(name),
+            definition: Some(self.outline.definitions.len() - 1),
+            ..frame
+        });
+        j + 1
+    }
+
+    /// Handles an `if`, loop, `switch` or `catch`, whose bodies get their own context.
+    fn control(&mut self, i: usize) -> usize {
+        let context = self.frame().context;
+        let branch = Context {
+            conditional: true,
+            ..context
+        };
+        let (body, head) = match self.text(i) {
+            // `else if` is handled by the `if`
+            "else" if self.text(i + 1) == "if" => return i + 1,
+            "else" => (branch, None),
+            "do" => (
+                Context {
+                    in_loop: true,
+                    ..context
+                },
+                None,
+            ),
+            "finally" => (
+                Context {
+                    in_finally: true,
+                    ..context
+                },
+                None,
+            ),
+            "catch" => (
+                Context {
+                    in_except: true,
+                    ..context
+                },
+                Some(context),
+            ),
+            "for" => (
+                Context {
+                    in_loop: true,
+                    conditional: true,
+                    ..context
+                },
+                Some(context),
+            ),
+            "while" => (
+                Context {
+                    in_loop: true,
+                    conditional: true,
+                    ..context
+                },
+                Some(Context {
+                    in_loop: true,
+                    ..context
+                }),
+            ),
+            _ => (branch, Some(context)),
+        };
+        let mut paren = i + 1;
+        if self.text(paren) == "await" {
+            paren += 1;
+        }
+        match head {
+            Some(head) if self.token(paren) == Some(Token::OpenParen) => {
+                let Some(close) = self.matches[paren] else {
+                    return i + 1;
+                };
+                self.pending.push((close + 1, body));
+                self.open(paren, FrameKind::Paren);
+                self.frames.last_mut().expect("just pushed").context = head;
+                paren + 1
+            }
+            // `catch {` without a binding
+            Some(_) if self.text(i) == "catch" => {
+                self.pending.push((i + 1, body));
+                i + 1
+            }
+            Some(_) => i + 1,
+            None => {
+                self.pending.push((i + 1, body));
+                i + 1
+            }
+        }
+    }
+
+    /// Handles `new Store()`, which constructs the class.
+    fn construct(&mut self, i: usize) -> usize {
+        let mut parts = Vec::new();
+        let mut j = i + 1;
+        while self.token(j) == Some(Token::Name) {
+            parts.push(self.text(j).to_owned());
+            if self.token(j + 1) != Some(Token::Dot) {
+                break;
+            }
+            j += 2;
+        }
+        if parts.is_empty() || parts == ["target"] {
+            return i + 1;
+        }
+        self.add_call(Target::New(parts), EdgeKind::Instantiation, i + 1);
+        j + 1
+    }
+
+    /// Handles `const`, `let` and `var`, keeping track of variables bound with `new`.
+    fn declaration(&mut self, i: usize) -> usize {
+        let name = i + 1;
+        // Destructuring, e.g. `const [todos, setTodos] = useState([])`
+        if matches!(
+            self.token(name),
+            Some(Token::OpenBrace | Token::OpenBracket)
+        ) {
+            if let Some(close) = self.matches[name] {
+                let function = self.frame().function.clone();
+                for variable in self.pattern_names(name + 1..close) {
+                    self.variables(function.as_deref())
+                        .entry(variable)
+                        .or_default()
+                        .unknown = true;
+                }
+            }
+            return i + 1;
+        }
+        if self.token(name) != Some(Token::Name) {
+            return i + 1;
+        }
+        let variable = self.text(name).to_owned();
+        // Skip a type annotation, e.g. `const store: Store = new Store()`
+        let mut assign = name + 1;
+        if self.token(assign) == Some(Token::Colon) {
+            while assign < self.tokens.len().min(name + 16)
+                && !matches!(
+                    self.tokens[assign].token,
+                    Token::Assign | Token::Semicolon | Token::OpenBrace
+                )
+            {
+                assign += 1;
+            }
+        }
+        if self.token(assign) != Some(Token::Assign) {
+            return name + 1;
+        }
+        let mut class = Vec::new();
+        if self.text(assign + 1) == "new" {
+            let mut j = assign + 2;
+            while self.token(j) == Some(Token::Name) {
+                class.push(self.text(j).to_owned());
+                if self.token(j + 1) != Some(Token::Dot) {
+                    break;
+                }
+                j += 2;
+            }
+        }
+        let function = self.frame().function.clone();
+        let variable = self
+            .variables(function.as_deref())
+            .entry(variable)
+            .or_default();
+        if class.is_empty() {
+            variable.unknown = true;
+        } else if !variable.classes.contains(&class) {
+            variable.classes.push(class);
+        }
+        // The name itself is skipped, so it isn't taken for a reference
+        name + 1
+    }
+
+    /// The variables of a function, or of the top level if `function` is `None`.
+    fn variables(&mut self, function: Option<&str>) -> &mut HashMap<String, Variable> {
+        match function {
+            Some(function) => {
+                &mut self
+                    .outline
+                    .scopes
+                    .get_mut(function)
+                    .expect("functions get a scope when they are defined")
+                    .variables
+            }
+            None => &mut self.outline.variables,
+        }
+    }
+
+    /// The names bound by a parameter list or a destructuring pattern, in the tokens of
+    /// `range`. Type annotations, default values and the keys of renamed properties are left
+    /// out.
+    fn pattern_names(&self, range: Range<usize>) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut braces = Vec::new();
+        let mut j = range.start;
+        while j < range.end {
+            let token = self.tokens[j].token;
+            match token {
+                // Parameter types and calls in default values
+                Token::OpenParen => j = self.matches[j].unwrap_or(range.end),
+                Token::OpenBrace | Token::OpenBracket => braces.push(token),
+                Token::CloseBrace | Token::CloseBracket => {
+                    braces.pop();
+                }
+                Token::Name => {
+                    let starts = j == range.start
+                        || matches!(
+                            self.tokens[j - 1].token,
+                            Token::Comma | Token::OpenBrace | Token::OpenBracket
+                        )
+                        || self.text(j - 1) == "..."
+                        // The new name of a renamed property, e.g. `{ id: userId }`
+                        || (self.tokens[j - 1].token == Token::Colon
+                            && braces.last() == Some(&Token::OpenBrace));
+                    let next = (j + 1 < range.end).then(|| self.tokens[j + 1].token);
+                    let ends = match next {
+                        None | Some(Token::Comma | Token::CloseBrace | Token::CloseBracket) => true,
+                        Some(Token::Assign) => true,
+                        // A key in an object pattern, or the type of a parameter
+                        Some(Token::Colon) => braces.last() != Some(&Token::OpenBrace),
+                        Some(Token::Operator) => self.text(j + 1) == "?",
+                        _ => false,
+                    };
+                    if starts && ends {
+                        names.push(self.text(j).to_owned());
+                    }
+                }
+                _ => {}
+            }
+            j += 1;
+        }
+        names
+    }
+
+    /// Handles an `import` declaration.
+    fn import(&mut self, i: usize) -> usize {
+        let mut bindings = Vec::new();
+        let mut j = i + 1;
+        if self.text(j) == "type" && self.text(j + 1) != "from" {
+            j += 1;
+        }
+        let mut specifier = None;
+        while j < self.tokens.len() {
+            match self.tokens[j].token {
+                Token::String => {
+                    specifier = Some(unquote(self.text(j)).to_owned());
+                    j += 1;
+                    break;
+                }
+                Token::Star if self.text(j + 1) == "as" => {
+                    bindings.push((self.text(j + 2).to_owned(), None));
+                    j += 3;
+                    continue;
+                }
+                Token::OpenBrace => {
+                    let close = self.matches[j].unwrap_or(j);
+                    bindings.extend(self.named_bindings(j + 1, close, "as"));
+                    j = close;
+                }
+                Token::Name if self.text(j) != "from" => {
+                    bindings.push((self.text(j).to_owned(), Some("default".to_owned())));
+                }
+                Token::Semicolon => break,
+                _ => {}
+            }
+            j += 1;
+        }
+        if let Some(specifier) = specifier {
+            for (local, name) in bindings {
+                let specifier = specifier.clone();
+                self.outline
+                    .imports
+                    .insert(local, Import { specifier, name });
+            }
+        }
+        j
+    }
+
+    /// Reads `a, b as c, type d` between braces into `(local, imported)` pairs. `rename` is
+    /// `as` for imports and `:` for destructuring.
+    fn named_bindings(
+        &self,
+        start: usize,
+        end: usize,
+        rename: &str,
+    ) -> Vec<(String, Option<String>)> {
+        let mut bindings = Vec::new();
+        let mut j = start;
+        while j < end {
+            if self.token(j) == Some(Token::Name) && self.text(j) != "type" {
+                let imported = self.text(j).to_owned();
+                if self.text(j + 1) == rename && self.token(j + 2) == Some(Token::Name) {
+                    bindings.push((self.text(j + 2).to_owned(), Some(imported)));
+                    j += 3;
+                    continue;
+                }
+                bindings.push((imported.clone(), Some(imported)));
+            }
+            j += 1;
+        }
+        bindings
+    }
+
+    /// Handles `const api = require("./api")` and `const { load } = require("./api")`.
+    fn require(&mut self, i: usize) {
+        if self.token(i + 2) != Some(Token::String) || i < 2 {
+            return;
+        }
+        let specifier = unquote(self.text(i + 2)).to_owned();
+        if self.token(i - 1) != Some(Token::Assign) {
+            return;
+        }
+        let bindings = match self.tokens[i - 2].token {
+            Token::Name => vec![(self.text(i - 2).to_owned(), None)],
+            Token::CloseBrace => {
+                let open = self.matches[i - 2].unwrap_or(i - 2);
+                self.named_bindings(open + 1, i - 2, ":")
+            }
+            _ => return,
+        };
+        for (local, name) in bindings {
+            let specifier = specifier.clone();
+            self.outline
+                .imports
+                .insert(local, Import { specifier, name });
+        }
+    }
+
+    /// Handles `export`. Re-exports are skipped, and what a default export is named is
+    /// recorded.
+    fn export(&mut self, i: usize) -> usize {
+        match self.token(i + 1) {
+            Some(Token::OpenBrace) => {
+                let close = self.matches[i + 1].unwrap_or(i + 1);
+                if self.text(close + 1) == "from" {
+                    return close + 3;
+                }
+                close + 1
+            }
+            Some(Token::Star) => {
+                let mut j = i + 1;
+                while j < self.tokens.len() && self.tokens[j].token != Token::String {
+                    j += 1;
+                }
+                j + 1
+            }
+            _ if self.text(i + 1) == "default" => {
+                self.set_default(i + 2);
+                i + 2
+            }
+            _ => i + 1,
+        }
+    }
+
+    /// Records what a default export starting at `i` is named.
+    fn set_default(&mut self, i: usize) {
+        let mut j = i;
+        if self.text(j) == "async" {
+            j += 1;
+        }
+        let name = match self.text(j) {
+            "function" | "class" => {
+                let mut name = j + 1;
+                if self.token(name) == Some(Token::Star) {
+                    name += 1;
+                }
+                match self.token(name) {
+                    Some(Token::Name) if !matches!(self.text(name), "extends" | "implements") => {
+                        self.text(name)
+                    }
+                    _ => "default",
+                }
+            }
+            text if self.token(j) == Some(Token::Name)
+                && !matches!(self.token(j + 1), Some(Token::OpenParen | Token::Dot)) =>
+            {
+                text
+            }
+            _ => "default",
+        };
+        self.outline.default = Some(name.to_owned());
+    }
+
+    /// Skips a TypeScript declaration that only has types in it.
+    fn skip_statement(&self, i: usize) -> usize {
+        let mut j = i + 1;
+        while j < self.tokens.len() {
+            match self.tokens[j].token {
+                Token::Semicolon => return j + 1,
+                Token::OpenBrace if self.text(i) != "type" => {
+                    return self.matches[j].map_or(j + 1, |close| close + 1)
+                }
+                token if is_open(token) => j = self.matches[j].unwrap_or(j),
+                token if is_close(token) => return j,
+                _ if j > i + 2
+                    && self.tokens[j].newline
+                    && !continues_after(self.tokens[j - 1].token)
+                    && !continues_before(self.tokens[j].token) =>
+                {
+                    return j
+                }
+                _ => {}
+            }
+            j += 1;
+        }
+        j
+    }
+}
+
+fn unquote(text: &str) -> &str {
+    text.get(1..text.len().saturating_sub(1)).unwrap_or("")
+}
+
+/// Links up the calls of all files, once everything they could refer to is known.
+struct Resolver<'a> {
+    outlines: &'a [Outline],
+    modules: HashMap<&'a str, &'a Outline>,
+    definitions: HashMap<&'a str, NodeKind>,
+    /// The resolved base class of each class that has one
+    bases: HashMap<&'a str, String>,
+    /// Qualified names of what each top-level name stands for, in any file
+    globals: HashMap<&'a str, Vec<&'a str>>,
+    /// The names of all methods
+    method_names: HashSet<&'a str>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(outlines: &'a [Outline]) -> Self {
+        let mut resolver = Self {
+            outlines,
+            modules: outlines
+                .iter()
+                .map(|outline| (outline.module.as_str(), outline))
+                .collect(),
+            definitions: HashMap::new(),
+            bases: HashMap::new(),
+            globals: HashMap::new(),
+            method_names: HashSet::new(),
+        };
+        for outline in outlines {
+            for definition in &outline.definitions {
+                resolver
+                    .definitions
+                    .insert(&definition.name, definition.kind);
+                if definition.kind == NodeKind::Method {
+                    if let Some((_, name)) = definition.name.rsplit_once('.') {
+                        resolver.method_names.insert(name);
+                    }
+                }
+            }
+            for (name, qualified) in &outline.names {
+                resolver.globals.entry(name).or_default().push(qualified);
+            }
+        }
+        for outline in outlines {
+            for class in &outline.classes {
+                let Some(base) = &class.base else {
+                    continue;
+                };
+                if let Some((base, _)) =
+                    resolver.resolve_path(outline, class.scope.as_deref(), base)
+                {
+                    resolver.bases.insert(&class.name, base);
+                }
+            }
+        }
+        resolver
+    }
+
+    /// Finds what `name` stands for in a function, or at the top level if `scope` is `None`:
+    /// a definition in an enclosing function or the file, an import, or a definition at the
+    /// top level of another file, for scripts that share globals.
+    fn resolve_name(
+        &self,
+        outline: &Outline,
+        scope: Option<&str>,
+        name: &str,
+    ) -> Option<(String, Category)> {
+        let mut current = scope;
+        while let Some(function) = current {
+            let scope = outline.scopes.get(function)?;
+            if let Some(qualified) = scope.locals.get(name) {
+                return Some((qualified.clone(), Category::Project));
+            }
+            current = scope.parent.as_deref();
+        }
+        if let Some(qualified) = outline.names.get(name) {
+            return Some((qualified.clone(), Category::Project));
+        }
+        if let Some(import) = outline.imports.get(name) {
+            return Some(self.resolve_import(outline, import));
+        }
+        match self.globals.get(name).map(Vec::as_slice) {
+            Some([qualified]) => Some((qualified.to_string(), Category::Project)),
+            _ => None,
+        }
+    }
+
+    /// Resolves a dotted path, e.g. `api.users.load`, through modules that are imported whole.
+    fn resolve_path(
+        &self,
+        outline: &Outline,
+        scope: Option<&str>,
+        parts: &[String],
+    ) -> Option<(String, Category)> {
+        let (first, rest) = parts.split_first()?;
+        let (mut resolved, category) = self.resolve_name(outline, scope, first)?;
+        for part in rest {
+            resolved = self.member(&resolved, part);
+        }
+        Some((resolved, category))
+    }
+
+    /// The qualified name of a member of a module or class.
+    fn member(&self, owner: &str, name: &str) -> String {
+        if let Some(qualified) = self
+            .modules
+            .get(owner)
+            .and_then(|module| module.names.get(name))
+        {
+            return qualified.clone();
+        }
+        self.lookup(owner, name)
+            .unwrap_or_else(|| format!("{owner}.{name}"))
+    }
+
+    fn resolve_import(&self, outline: &Outline, import: &Import) -> (String, Category) {
+        let Some(target) = self.resolve_specifier(outline, &import.specifier) else {
+            let specifier = &import.specifier;
+            let package = specifier.strip_prefix("node:").unwrap_or(specifier);
+            let category = if NODE_MODULES.binary_search(&package).is_ok() {
+                Category::Stdlib
+            } else {
+                Category::ThirdParty
+            };
+            let name = match import.name.as_deref() {
+                None | Some("default") => specifier.clone(),
+                Some(name) => format!("{specifier}.{name}"),
+            };
+            return (name, category);
+        };
+        let name = match import.name.as_deref() {
+            None => target.module.clone(),
+            Some("default") => target
+                .default
+                .as_ref()
+                .and_then(|name| target.names.get(name))
+                .cloned()
+                .unwrap_or_else(|| format!("{}.default", target.module)),
+            Some(name) => self.member(&target.module, name),
+        };
+        (name, Category::Project)
+    }
+
+    /// Finds the analyzed module an import specifier refers to. Relative specifiers are
+    /// resolved from the importing file, and others are tried as paths from the analyzed
+    /// directory, for path aliases like `@/components`.
+    fn resolve_specifier(&self, outline: &Outline, specifier: &str) -> Option<&'a Outline> {
+        let path = if specifier.starts_with('.') {
+            let directory = Path::new(&outline.module).parent().unwrap_or(Path::new(""));
+            let mut parts = Vec::new();
+            for component in directory.join(specifier).components() {
+                match component {
+                    Component::ParentDir => {
+                        parts.pop();
+                    }
+                    Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+                    _ => {}
+                }
+            }
+            parts.join("/")
+        } else {
+            let alias = ["@/", "~/"]
+                .iter()
+                .find_map(|alias| specifier.strip_prefix(alias));
+            alias.unwrap_or(specifier).to_owned()
+        };
+        let without_extension = Path::new(&path).with_extension("");
+        [path.as_str(), &without_extension.to_string_lossy()]
+            .iter()
+            .find_map(|path| self.modules.get(path.trim_end_matches("/index")).copied())
+    }
+
+    /// Finds the method a call to `method` on an instance of `class` goes to, following the
+    /// chain of base classes.
+    fn lookup(&self, class: &str, method: &str) -> Option<String> {
+        let mut current = Some(class.to_owned());
+        // Bases can be mistaken for each other, and end up in a cycle
+        for _ in 0..32 {
+            let class = current?;
+            let qualified = format!("{class}.{method}");
+            if self.definitions.get(qualified.as_str()) == Some(&NodeKind::Method) {
+                return Some(qualified);
+            }
+            current = self.bases.get(class.as_str()).cloned();
+        }
+        None
+    }
+
+    /// Where a call goes to: the callee, where it comes from, and the candidates if it could
+    /// go to more than one class's method.
+    fn resolve_call(&self, outline: &Outline, call: &Call) -> (String, Category, Vec<String>) {
+        let scope = call.caller.as_deref();
+        let class = scope
+            .and_then(|scope| outline.scopes.get(scope))
+            .and_then(|scope| scope.class.as_deref());
+        match &call.target {
+            Target::Defined(name) => (name.clone(), Category::Project, Vec::new()),
+            Target::Name(name) => match self.resolve_name(outline, scope, name) {
+                Some((callee, category)) => (callee, category, Vec::new()),
+                None if GLOBALS.binary_search(&name.as_str()).is_ok() => {
+                    (name.clone(), Category::Builtin, Vec::new())
+                }
+                // Parameters and variables only mean something in the function they are in
+                None => match self.declaring_scope(outline, scope, name) {
+                    Some(owner) => (format!("{owner}.<{name}>"), Category::Project, Vec::new()),
+                    None => (name.clone(), Category::Project, Vec::new()),
+                },
+            },
+            Target::New(parts) => match self.resolve_path(outline, scope, parts) {
+                Some((class, category)) => {
+                    let callee = self.lookup(&class, "constructor").unwrap_or(class);
+                    (callee, category, Vec::new())
+                }
+                None if GLOBALS.binary_search(&parts[0].as_str()).is_ok() => {
+                    (parts.join("."), Category::Builtin, Vec::new())
+                }
+                None => (parts.join("."), Category::Project, Vec::new()),
+            },
+            Target::Member(receiver, method) => {
+                self.resolve_method(outline, scope, class, receiver, method)
+            }
+        }
+    }
+
+    fn resolve_method(
+        &self,
+        outline: &Outline,
+        scope: Option<&str>,
+        class: Option<&str>,
+        receiver: &[String],
+        method: &str,
+    ) -> (String, Category, Vec<String>) {
+        let in_class = |class: &str| {
+            let callee = self
+                .lookup(class, method)
+                .unwrap_or_else(|| format!("{class}.{method}"));
+            (callee, Category::Project, Vec::new())
+        };
+        match (receiver, class) {
+            ([this], Some(class)) if this == "this" => return in_class(class),
+            ([this], Some(class)) if this == "super" => {
+                if let Some(base) = self.bases.get(class) {
+                    return in_class(base);
+                }
+            }
+            _ => {}
+        }
+
+        if let [name] = receiver {
+            if let Some(variable) = self.variable(outline, scope, name) {
+                let classes = variable
+                    .classes
+                    .iter()
+                    .filter_map(|class| self.resolve_path(outline, scope, class))
+                    .map(|(class, _)| class)
+                    .collect::<Vec<_>>();
+                match classes.as_slice() {
+                    [class] if !variable.unknown => return in_class(class),
+                    // Guessing one of the classes would hide that the others are possible
+                    [_, _, ..] if !variable.unknown => {
+                        let candidates = classes
+                            .iter()
+                            .map(|class| in_class(class).0)
+                            .collect::<Vec<_>>();
+                        let owner = class.unwrap_or(&outline.module);
+                        let callee = format!("{owner}.<{name}>.{method}");
+                        return (callee, Category::Project, candidates);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if let Some((owner, category)) = self.resolve_path(outline, scope, receiver) {
+            return (self.member(&owner, method), category, Vec::new());
+        }
+        let is_global = GLOBALS.binary_search(&receiver[0].as_str()).is_ok();
+        let receiver = receiver.join(".");
+        if is_global {
+            return (
+                format!("{receiver}.{method}"),
+                Category::Builtin,
+                Vec::new(),
+            );
+        }
+        // The receiver's type isn't known, so this is only a guess. A method the project
+        // defines is more likely than a builtin one with the same name
+        let category = if !self.method_names.contains(method)
+            && BUILTIN_METHODS.binary_search(&method).is_ok()
+        {
+            Category::Builtin
+        } else {
+            Category::Project
+        };
+        // Receivers like `this.api` mean something else in every class, and `<expr>` in
+        // every function, so they're kept apart by where they are used
+        let owner = match scope {
+            Some(function) if receiver.contains("<expr>") => function,
+            _ => class.unwrap_or(&outline.module),
+        };
+        let callee = match receiver.as_str() {
+            "<expr>" | "<literal>" => format!("{owner}.{receiver}.{method}"),
+            _ => format!("{owner}.<{receiver}>.{method}"),
+        };
+        (callee, category, Vec::new())
+    }
+
+    /// The function that declares a parameter or variable `name` seen in `scope`, or the
+    /// module for variables at the top level.
+    fn declaring_scope<'o>(
+        &self,
+        outline: &'o Outline,
+        scope: Option<&'o str>,
+        name: &str,
+    ) -> Option<&'o str> {
+        let mut current = scope;
+        while let Some(function) = current {
+            let scope = outline.scopes.get(function)?;
+            if scope.variables.contains_key(name) {
+                return Some(function);
+            }
+            current = scope.parent.as_deref();
+        }
+        outline
+            .variables
+            .contains_key(name)
+            .then_some(outline.module.as_str())
+    }
+
+    fn variable<'o>(
+        &self,
+        outline: &'o Outline,
+        scope: Option<&str>,
+        name: &str,
+    ) -> Option<&'o Variable> {
+        let mut current = scope;
+        while let Some(function) = current {
+            let scope = outline.scopes.get(function)?;
+            if let Some(variable) = scope.variables.get(name) {
+                return Some(variable);
+            }
+            current = scope.parent.as_deref();
+        }
+        outline.variables.get(name)
+    }
+
+    fn build_graph(&self, options: &GraphOptions) -> CallGraph {
+        let mut graph = CallGraph::default();
+        for outline in self.outlines {
+            for definition in &outline.definitions {
+                let parent = definition
+                    .parent
+                    .as_ref()
+                    .and_then(|parent| graph.find(parent));
+                let location = definition.location.clone();
+                graph.define(&definition.name, definition.kind, location, parent);
+            }
+        }
+
+        for outline in self.outlines {
+            for call in &outline.calls {
+                let caller = match &call.caller {
+                    Some(caller) => graph.find(caller).expect("callers are defined above"),
+                    None if options.exclude_module_scope => continue,
+                    None => match graph.find(&outline.module) {
+                        Some(module) => module,
+                        None => {
+                            let location = outline.location(1, outline.lines);
+                            graph.define(&outline.module, NodeKind::Module, location, None)
+                        }
+                    },
+                };
+                let (callee, category, candidates) = self.resolve_call(outline, call);
+                // Any name can be passed around, so references are only kept to functions
+                if call.kind == EdgeKind::Reference
+                    && !matches!(
+                        self.definitions.get(callee.as_str()),
+                        Some(NodeKind::Function | NodeKind::Method | NodeKind::Lambda)
+                    )
+                {
+                    continue;
+                }
+                let receiver = match &call.target {
+                    Target::Member(receiver, _) => Some(receiver.join(".")),
+                    _ => None,
+                };
+                let site = CallSite {
+                    receiver,
+                    line: call.line,
+                    column: call.column,
+                    context: call.context,
+                };
+                // Until a definition turns up, callees are only known by where they come from
+                let kind = match category {
+                    Category::Builtin => NodeKind::Builtin,
+                    Category::Stdlib | Category::ThirdParty => NodeKind::External,
+                    Category::Project => NodeKind::Function,
+                };
+                let callee = graph.add_node(&callee, kind, category);
+                if !candidates.is_empty() {
+                    graph.ambiguities.push(Ambiguity {
+                        caller,
+                        call: graph.node(callee).name.clone(),
+                        candidates,
+                        site: site.clone(),
+                    });
+                }
+                graph.edges.push(Edge {
+                    caller,
+                    callee,
+                    kind: call.kind,
+                    sites: vec![site],
+                    count: None,
+                });
+            }
+        }
+
+        self.add_dispatches(&mut graph);
+        graph.merge_edges();
+        graph
+    }
+
+    /// Adds an edge from every method to the methods that override it, since a call to it may
+    /// end up in any of them.
+    fn add_dispatches(&self, graph: &mut CallGraph) {
+        let mut methods = self
+            .definitions
+            .iter()
+            .filter(|(_, kind)| **kind == NodeKind::Method)
+            .filter_map(|(name, _)| name.rsplit_once('.'))
+            .collect::<Vec<_>>();
+        methods.sort();
+        for (class, name) in methods {
+            let Some(overridden) = self
+                .bases
+                .get(class)
+                .and_then(|base| self.lookup(base, name))
+            else {
+                continue;
+            };
+            let method = format!("{class}.{name}");
+            let (Some(overridden), Some(method)) = (graph.find(&overridden), graph.find(&method))
+            else {
+                continue;
+            };
+            let Some(location) = &graph.node(method).location else {
+                continue;
+            };
+            let site = CallSite {
+                receiver: None,
+                line: location.start_line,
+                column: 1,
+                context: Context::default(),
+            };
+            graph.edges.push(Edge {
+                caller: overridden,
+                callee: method,
+                kind: EdgeKind::Dispatch,
+                sites: vec![site],
+                count: None,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::generate_graph_from_path;
+
+    fn edge<'g>(graph: &'g CallGraph, caller: &str, callee: &str) -> &'g Edge {
+        graph
+            .edge(caller, callee)
+            .unwrap_or_else(|| panic!("no edge from {caller} to {callee}"))
+    }
+
+    #[test]
+    fn webapp() {
+        let graph =
+            generate_graph_from_path(Path::new("assets/webapp"), &GraphOptions::default()).unwrap();
+
+        // Imports of default exports, named exports and CommonJS modules
+        let component = "components/TodoList.TodoList";
+        let effect = "components/TodoList.TodoList.<arrow@L10>";
+        edge(&graph, effect, "api/client.createClient");
+        edge(&graph, "api/client.Client.get", "utils.formatQuery");
+        let join = edge(
+            &graph,
+            "components/TodoList.TodoList.render",
+            "utils.joinPath",
+        );
+        assert_eq!(join.kind, EdgeKind::AttributeCall);
+        let hook = edge(&graph, component, "react.useState");
+        assert_eq!(graph.node(hook.callee).category, Category::ThirdParty);
+        edge(&graph, "utils.joinPath", "path.join");
+
+        // Classes, `super` and inherited constructors
+        let new = edge(
+            &graph,
+            "api/client.createClient",
+            "api/client.Client.constructor",
+        );
+        assert_eq!(new.kind, EdgeKind::Instantiation);
+        edge(
+            &graph,
+            "api/client.TodoClient.list",
+            "api/client.TodoClient.get",
+        );
+        edge(&graph, "api/client.TodoClient.get", "api/client.Client.get");
+        let dispatch = edge(&graph, "api/client.Client.get", "api/client.TodoClient.get");
+        assert_eq!(dispatch.kind, EdgeKind::Dispatch);
+
+        // Contexts
+        let error = edge(&graph, "api/client.Client.get", "Error");
+        assert!(error.sites[0].context.conditional);
+        let log = edge(&graph, "api/client.TodoClient.get", "console.error");
+        assert!(log.is_error_only());
+        let render = edge(&graph, "index", "react-dom.render");
+        let context = render.sites[0].context;
+        assert!(context.in_loop && context.conditional);
+
+        // Callbacks that are passed around
+        let callback = edge(&graph, component, effect);
+        assert_eq!(callback.kind, EdgeKind::Reference);
+        edge(&graph, component, "components/TodoList.TodoList.render");
+        edge(&graph, "index", component);
+
+        // Parameters and destructured variables belong to the function that binds them
+        edge(
+            &graph,
+            "components/TodoList.TodoList.<arrow@L10>.<arrow@L12>",
+            "components/TodoList.TodoList.<setTodos>",
+        );
+        edge(
+            &graph,
+            "components/TodoList.TodoList.render.<arrow@L16>",
+            "components/TodoList.TodoList.<onSelect>",
+        );
+        assert!(graph.find("setTodos").is_none());
+        let list = edge(&graph, effect, "components/TodoList.<client>.list");
+        assert_eq!(graph.node(list.callee).category, Category::Project);
+        let then = edge(&graph, effect, "components/TodoList.<client.list()>.then");
+        assert_eq!(graph.node(then.callee).category, Category::Builtin);
+    }
+}
@@ -1,12 +1,52 @@
 #![allow(dead_code)]
 
+mod builtins;
+mod c_frontend;
+mod call_graph;
+mod error;
+mod frontend;
 mod generate_graph;
+mod imports;
+mod js_frontend;
+mod profile;
+mod rust_frontend;
+mod sources;
 mod visualize;
 
-use anyhow::Result;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use frontend::GraphOptions;
+use glob::Pattern;
 
 fn main() -> Result<()> {
-    visualize::init("./assets/scc.py");
+    // Usage: callgraph-viz [PATH] [--include GLOB]... [--exclude GLOB]... [--hide CATEGORY]...
+    //                      [--no-module-scope] [--lenient]
+    // PATH can also be a profile written by cProfile, e.g. `assets/profile/pipeline.prof`
+    let mut path = PathBuf::from("./assets/scc.py");
+    let mut options = GraphOptions::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--include" => {
+                let glob = args.next().context("--include needs a glob")?;
+                options.sources.include.push(Pattern::new(&glob)?);
+            }
+            "--exclude" => {
+                let glob = args.next().context("--exclude needs a glob")?;
+                options.sources.exclude.push(Pattern::new(&glob)?);
+            }
+            "--hide" => {
+                let category = args.next().context("--hide needs a category")?;
+                options.hidden.insert(category.parse()?);
+            }
+            "--no-module-scope" => options.exclude_module_scope = true,
+            "--lenient" => options.lenient = true,
+            _ => path = PathBuf::from(arg),
+        }
+    }
+
+    visualize::init(path, options);
 
     Ok(())
 }
@@ -0,0 +1,734 @@
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    builtins,
+    call_graph::{CallGraph, Category, Edge, EdgeKind, Location, Node, NodeKind},
+    error::GraphError,
+    frontend::{Frontend, GraphOptions},
+    generate_graph::Python,
+    imports,
+};
+
+/// Extensions of the files `cProfile` and `pstats` write
+const EXTENSIONS: &[&str] = &["prof", "pstats"];
+
+/// Whether a file is a profile, rather than source code to analyze.
+pub fn is_profile(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .is_some_and(|extension| EXTENSIONS.iter().any(|&ext| extension == ext))
+}
+
+/// Builds the graph of the calls that were made while a profile was recorded, out of the
+/// marshalled stats that `cProfile` and `pstats.Stats.dump_stats` write. Nodes have the
+/// cumulative time spent in them, and edges the number of calls.
+///
+/// Profiles only know functions by file, line and name, so functions whose file can be read
+/// are named like the Python frontend names them, e.g. `pkg.module.Class.method`. Others are
+/// named by their module, e.g. `pkg.module.method`, and builtins like the Python frontend
+/// names their callees, e.g. `len` or `list.append`.
+pub fn load_profile(path: &Path) -> Result<CallGraph, GraphError> {
+    let bytes = std::fs::read(path).map_err(|error| GraphError::io(path, &error))?;
+    let mut reader = Reader {
+        path,
+        bytes: &bytes,
+        position: 0,
+        refs: Vec::new(),
+    };
+    let value = reader.value()?;
+    let mut stats = parse_stats(value).ok_or_else(|| GraphError::Syntax {
+        file: path.to_path_buf(),
+        line: 1,
+        column: 1,
+        message: "not a profile: expected a dict of function stats".to_owned(),
+    })?;
+    stats.sort_by(|a, b| a.function.cmp(&b.function));
+    // The profiled code stops the profiler, which isn't one of its calls
+    stats.retain(|entry| !is_profiler(&entry.function));
+    for entry in &mut stats {
+        entry.callers.retain(|(caller, _)| !is_profiler(caller));
+    }
+
+    let mut names = Names {
+        profile_dir: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+        graphs: HashMap::new(),
+        named: HashMap::new(),
+    };
+    for entry in &stats {
+        names.name(&entry.function);
+        for (caller, _) in &entry.callers {
+            names.name(caller);
+        }
+    }
+    names.disambiguate();
+
+    let mut graph = CallGraph::default();
+    let add = |graph: &mut CallGraph, function: &Function| {
+        let named = &names.named[function];
+        match &named.location {
+            Some(location) => graph.define(&named.name, named.kind, location.clone(), None),
+            None => graph.add_node(&named.name, named.kind, named.category),
+        }
+    };
+    for entry in &stats {
+        let callee = add(&mut graph, &entry.function);
+        let time = &mut graph.node_mut(callee).cumulative_time;
+        *time = Some(time.unwrap_or(0.) + entry.cumulative_time);
+        for (caller, count) in &entry.callers {
+            let caller = add(&mut graph, caller);
+            graph.edges.push(Edge {
+                caller,
+                callee,
+                kind: EdgeKind::Call,
+                sites: Vec::new(),
+                count: Some(*count),
+            });
+        }
+    }
+    // Definitions stay inside the ones around them, if those ran too
+    for named in names.named.values() {
+        let parent = named
+            .parent
+            .as_deref()
+            .and_then(|parent| graph.find(parent));
+        if let (Some(id), Some(parent)) = (graph.find(&named.name), parent) {
+            graph.node_mut(id).parent = Some(parent);
+        }
+    }
+    graph.merge_edges();
+    Ok(graph)
+}
+
+/// A value read from a marshalled file. Lists and sets are read as tuples, and bytes as
+/// strings.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    None,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Tuple(Vec<Value>),
+    Dict(Vec<(Value, Value)>),
+}
+
+impl Value {
+    fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Self::Int(int) => Some(int as f64),
+            Self::Float(float) => Some(float),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the format of Python's `marshal` module, for the types a profile is made of.
+struct Reader<'b> {
+    path: &'b Path,
+    bytes: &'b [u8],
+    position: usize,
+    /// Values that later ones can refer back to
+    refs: Vec<Value>,
+}
+
+impl<'b> Reader<'b> {
+    fn error(&self, message: impl Into<String>) -> GraphError {
+        GraphError::Syntax {
+            file: self.path.to_path_buf(),
+            line: 1,
+            column: self.position + 1,
+            message: message.into(),
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'b [u8], GraphError> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| self.error("the file ends in the middle of a value"))?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, GraphError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, GraphError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes(
+            bytes.try_into().expect("4 bytes were taken"),
+        ))
+    }
+
+    fn i32(&mut self) -> Result<i32, GraphError> {
+        Ok(self.u32()? as i32)
+    }
+
+    fn len(&mut self) -> Result<usize, GraphError> {
+        Ok(self.u32()? as usize)
+    }
+
+    fn string(&mut self, len: usize) -> Result<Value, GraphError> {
+        let bytes = self.take(len)?;
+        Ok(Value::Str(String::from_utf8_lossy(bytes).into_owned()))
+    }
+
+    fn tuple(&mut self, len: usize) -> Result<Value, GraphError> {
+        // The length isn't trusted with an allocation, the file may be cut short
+        let mut items = Vec::with_capacity(len.min(1024));
+        for _ in 0..len {
+            items.push(self.value()?);
+        }
+        Ok(Value::Tuple(items))
+    }
+
+    fn value(&mut self) -> Result<Value, GraphError> {
+        let code = self.byte()?;
+        // Values with the high bit set can be referred back to. Their slot is taken before
+        // what's inside them is read.
+        let slot = (code & 0x80 != 0).then(|| {
+            self.refs.push(Value::None);
+            self.refs.len() - 1
+        });
+        let value = match code & 0x7f {
+            b'N' => Value::None,
+            b'T' => Value::Bool(true),
+            b'F' => Value::Bool(false),
+            b'i' => Value::Int(self.i32()?.into()),
+            b'I' => {
+                let bytes = self.take(8)?;
+                Value::Int(i64::from_le_bytes(
+                    bytes.try_into().expect("8 bytes were taken"),
+                ))
+            }
+            // Digits of 15 bits, least significant first, with the sign on the count
+            b'l' => {
+                let count = self.i32()?;
+                let mut int = 0i64;
+                for i in 0..count.unsigned_abs() {
+                    let bytes = self.take(2)?;
+                    let digit = i64::from(u16::from_le_bytes([bytes[0], bytes[1]]));
+                    int = digit
+                        .checked_shl(15 * i)
+                        .and_then(|digit| int.checked_add(digit))
+                        .ok_or_else(|| self.error("integer too large"))?;
+                }
+                Value::Int(if count < 0 { -int } else { int })
+            }
+            b'g' => {
+                let bytes = self.take(8)?;
+                Value::Float(f64::from_le_bytes(
+                    bytes.try_into().expect("8 bytes were taken"),
+                ))
+            }
+            b'f' => {
+                let len = self.byte()?.into();
+                let bytes = self.take(len)?;
+                let float = std::str::from_utf8(bytes)
+                    .ok()
+                    .and_then(|text| text.parse().ok())
+                    .ok_or_else(|| self.error("invalid float"))?;
+                Value::Float(float)
+            }
+            b's' | b't' | b'u' | b'a' | b'A' => {
+                let len = self.len()?;
+                self.string(len)?
+            }
+            b'z' | b'Z' => {
+                let len = self.byte()?.into();
+                self.string(len)?
+            }
+            b'(' | b'[' | b'<' | b'>' => {
+                let len = self.len()?;
+                self.tuple(len)?
+            }
+            b')' => {
+                let len = self.byte()?.into();
+                self.tuple(len)?
+            }
+            // Pairs up to a null
+            b'{' => {
+                let mut pairs = Vec::new();
+                while self.bytes.get(self.position) != Some(&b'0') {
+                    let key = self.value()?;
+                    pairs.push((key, self.value()?));
+                }
+                self.position += 1;
+                Value::Dict(pairs)
+            }
+            b'r' => {
+                let index = self.len()?;
+                self.refs
+                    .get(index)
+                    .cloned()
+                    .ok_or_else(|| self.error("refers to a value that isn't there"))?
+            }
+            code => {
+                return Err(self.error(format!(
+                    "values of type `{}` aren't supported",
+                    char::from(code)
+                )))
+            }
+        };
+        if let Some(slot) = slot {
+            self.refs[slot] = value.clone();
+        }
+        Ok(value)
+    }
+}
+
+/// A function as a profile knows it: its file, first line and name. Builtins are in the
+/// file `~`, on line 0.
+type Function = (String, i64, String);
+
+/// What a profile recorded for a function.
+struct Stats {
+    function: Function,
+    /// Seconds spent in the function and everything it called
+    cumulative_time: f64,
+    /// The functions that called it, and how many times they did
+    callers: Vec<(Function, usize)>,
+}
+
+fn function(value: &Value) -> Option<Function> {
+    match value {
+        Value::Tuple(items) => match items.as_slice() {
+            [Value::Str(file), Value::Int(line), Value::Str(name)] => {
+                Some((file.clone(), *line, name.clone()))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether a function is a method of the profiler itself, e.g. the `disable` that ends a
+/// profile.
+fn is_profiler((file, _, name): &Function) -> bool {
+    file == "~" && name.ends_with("of '_lsprof.Profiler' objects>")
+}
+
+/// Reads the stats of every function, from a dict of
+/// `function: (primitive calls, calls, own time, cumulative time, callers)`. The callers of
+/// `cProfile` have the same numbers for each caller, the ones of the older `profile` module
+/// only the number of calls.
+fn parse_stats(value: Value) -> Option<Vec<Stats>> {
+    let Value::Dict(pairs) = value else {
+        return None;
+    };
+    pairs
+        .iter()
+        .map(|(key, value)| {
+            let Value::Tuple(numbers) = value else {
+                return None;
+            };
+            let [_, _, _, cumulative_time, Value::Dict(callers)] = numbers.as_slice() else {
+                return None;
+            };
+            let callers = callers
+                .iter()
+                .map(|(caller, numbers)| {
+                    let count = match numbers {
+                        Value::Tuple(numbers) => numbers.get(1)?.as_f64()?,
+                        number => number.as_f64()?,
+                    };
+                    Some((function(caller)?, count as usize))
+                })
+                .collect::<Option<_>>()?;
+            Some(Stats {
+                function: function(key)?,
+                cumulative_time: cumulative_time.as_f64()?,
+                callers,
+            })
+        })
+        .collect()
+}
+
+/// What a function of a profile is called in the graph.
+struct Named {
+    name: String,
+    kind: NodeKind,
+    category: Category,
+    /// Where it's defined, if it's part of the project
+    location: Option<Location>,
+    /// The definition it's in
+    parent: Option<String>,
+    /// Whether it's only named by its module, without the classes or functions around it
+    by_module: bool,
+}
+
+/// Names the functions of a profile.
+struct Names {
+    /// Relative paths in a profile are tried relative to it too
+    profile_dir: PathBuf,
+    /// The graphs of the Python files that were read, to name functions after
+    graphs: HashMap<PathBuf, Option<CallGraph>>,
+    named: HashMap<Function, Named>,
+}
+
+impl Names {
+    fn name(&mut self, function: &Function) {
+        if self.named.contains_key(function) {
+            return;
+        }
+        let (file, line, name) = function;
+        let named = if file == "~" {
+            builtin(name)
+        } else if let Some(module) = file
+            .strip_prefix("<frozen ")
+            .and_then(|file| file.strip_suffix('>'))
+        {
+            external(&in_module(module, name))
+        } else {
+            self.python(file, *line, name)
+        };
+        self.named.insert(function.clone(), named);
+    }
+
+    fn python(&mut self, file: &str, line: i64, name: &str) -> Named {
+        let mut path = PathBuf::from(file);
+        if path.is_relative() && !path.is_file() && self.profile_dir.join(&path).is_file() {
+            path = self.profile_dir.join(path);
+        }
+        let category = path_category(&path);
+        if category != Category::Project {
+            let (module, _) = imports::module_name(&path);
+            return external(&in_module(&module, name));
+        }
+
+        let graph = self.graphs.entry(path.clone()).or_insert_with(|| {
+            let src = std::fs::read_to_string(&path).ok()?;
+            Python
+                .generate(vec![(path.clone(), src)], None, &GraphOptions::default())
+                .ok()
+        });
+        let line = usize::try_from(line).unwrap_or(0);
+        let (module, _) = imports::module_name(&path);
+        if let Some(graph) = graph {
+            let mut definitions = graph
+                .nodes()
+                .filter_map(|(_, node)| Some((node, node.location.as_ref()?)));
+            let parent = |node: &Node| node.parent.map(|parent| graph.node(parent).name.clone());
+            let definition = match name {
+                "<module>" => definitions.find(|(node, _)| node.kind == NodeKind::Module),
+                "<lambda>" => definitions.find(|(node, location)| {
+                    node.kind == NodeKind::Lambda && location.start_line == line
+                }),
+                // Comprehensions have no node of their own, so they are named like lambdas,
+                // after the innermost definition around them
+                _ if name.starts_with('<') => {
+                    let around = definitions
+                        .filter(|(_, location)| {
+                            (location.start_line..=location.end_line).contains(&line)
+                        })
+                        .min_by_key(|(_, location)| location.end_line - location.start_line);
+                    let comprehension = format!("{}@L{line}>", name.trim_end_matches('>'));
+                    let (name, parent) = match around {
+                        Some((node, _)) if node.kind != NodeKind::Module => (
+                            format!("{}.<locals>.{comprehension}", node.name),
+                            Some(node.name.clone()),
+                        ),
+                        _ => (in_module(&module, &comprehension), None),
+                    };
+                    return Named {
+                        name,
+                        kind: NodeKind::Function,
+                        category,
+                        location: Some(Location {
+                            file: path,
+                            start_line: line,
+                            end_line: line,
+                        }),
+                        parent,
+                        by_module: false,
+                    };
+                }
+                // The first line of a decorated function is its first decorator's, so the
+                // nearest definition at or after it is taken
+                _ => definitions
+                    .filter(|(node, location)| {
+                        location.start_line >= line && node.name.rsplit('.').next() == Some(name)
+                    })
+                    .min_by_key(|(_, location)| location.start_line),
+            };
+            if let Some((node, location)) = definition {
+                return Named {
+                    name: node.name.clone(),
+                    kind: node.kind,
+                    category,
+                    location: Some(location.clone()),
+                    parent: parent(node),
+                    by_module: false,
+                };
+            }
+        }
+
+        Named {
+            name: in_module(&module, name),
+            kind: match name {
+                "<module>" => NodeKind::Module,
+                "<lambda>" => NodeKind::Lambda,
+                _ => NodeKind::Function,
+            },
+            category,
+            location: Some(Location {
+                file: path,
+                start_line: line,
+                end_line: line,
+            }),
+            parent: None,
+            by_module: true,
+        }
+    }
+
+    /// Functions that were only named by their module can end up with the same name, e.g.
+    /// the `__init__` of two classes. Those get their line added. Other functions with the
+    /// same name are the same definition, and become one node.
+    fn disambiguate(&mut self) {
+        let mut counts = HashMap::<_, usize>::new();
+        for named in self.named.values() {
+            *counts.entry(named.name.clone()).or_default() += 1;
+        }
+        for ((_, line, _), named) in &mut self.named {
+            if named.by_module && counts[&named.name] > 1 {
+                named.name = format!("{}:{line}", named.name);
+            }
+        }
+    }
+}
+
+/// The name of a function in a module, or of the module's top-level code.
+fn in_module(module: &str, name: &str) -> String {
+    match name {
+        "<module>" => format!("<module {module}>"),
+        _ if module.is_empty() => name.to_owned(),
+        _ => format!("{module}.{name}"),
+    }
+}
+
+/// Where a Python file comes from: installed packages are third party, and the files of a
+/// Python installation are the standard library.
+fn path_category(path: &Path) -> Category {
+    let names = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if names
+        .iter()
+        .any(|&name| name == "site-packages" || name == "dist-packages")
+    {
+        Category::ThirdParty
+    } else if path.is_absolute()
+        && names.iter().any(|name| {
+            name.strip_prefix("python")
+                .is_some_and(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+        })
+    {
+        Category::Stdlib
+    } else {
+        Category::Project
+    }
+}
+
+/// A function built into the interpreter as `cProfile` labels it, e.g.
+/// `<built-in method builtins.len>` or `<method 'append' of 'list' objects>`. Older versions
+/// use braces instead. Ones outside of the `builtins` module are part of the standard library.
+fn builtin(label: &str) -> Named {
+    let label = label.trim_matches(|c| matches!(c, '<' | '>' | '{' | '}'));
+    let (name, category) = if let Some((method, owner)) = label
+        .strip_prefix("method '")
+        .and_then(|rest| rest.strip_suffix("' objects"))
+        .and_then(|rest| rest.split_once("' of '"))
+    {
+        let category = if builtins::is_builtin(owner) {
+            Category::Builtin
+        } else {
+            Category::Stdlib
+        };
+        (format!("{owner}.{method}"), category)
+    } else {
+        let qualified = label
+            .strip_prefix("built-in method ")
+            .or_else(|| label.strip_prefix("built-in function "))
+            .unwrap_or(label);
+        match qualified.strip_prefix("builtins.") {
+            Some(name) => (name.to_owned(), Category::Builtin),
+            None => (qualified.to_owned(), Category::Stdlib),
+        }
+    };
+    Named {
+        name,
+        kind: match category {
+            Category::Builtin => NodeKind::Builtin,
+            _ => NodeKind::External,
+        },
+        category,
+        location: None,
+        parent: None,
+        by_module: false,
+    }
+}
+
+fn external(name: &str) -> Named {
+    Named {
+        name: name.to_owned(),
+        kind: NodeKind::External,
+        category: if builtins::is_stdlib(name) {
+            Category::Stdlib
+        } else {
+            Category::ThirdParty
+        },
+        location: None,
+        parent: None,
+        by_module: true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge<'g>(graph: &'g CallGraph, caller: &str, callee: &str) -> &'g Edge {
+        graph
+            .edge(caller, callee)
+            .unwrap_or_else(|| panic!("no edge from {caller} to {callee}"))
+    }
+
+    fn read(bytes: &[u8]) -> Result<Value, GraphError> {
+        Reader {
+            path: Path::new("test.prof"),
+            bytes,
+            position: 0,
+            refs: Vec::new(),
+        }
+        .value()
+    }
+
+    #[test]
+    fn marshal() {
+        assert_eq!(read(b"i\x2a\x00\x00\x00").unwrap(), Value::Int(42));
+        // Two digits of 15 bits, with the sign on the count
+        let long = b"l\x02\x00\x00\x00\x01\x00\x01\x00";
+        assert_eq!(read(long).unwrap(), Value::Int(32769));
+        let negative = b"l\xfe\xff\xff\xff\x01\x00\x01\x00";
+        assert_eq!(read(negative).unwrap(), Value::Int(-32769));
+        let mut float = b"g".to_vec();
+        float.extend(1.5f64.to_le_bytes());
+        assert_eq!(read(&float).unwrap(), Value::Float(1.5));
+
+        // A short string that is stored for later, and a reference back to it
+        let tuple = b")\x02\xda\x03abcr\x00\x00\x00\x00";
+        let abc = Value::Str("abc".to_owned());
+        assert_eq!(read(tuple).unwrap(), Value::Tuple(vec![abc.clone(), abc]));
+        let dict = b"{z\x01ai\x01\x00\x00\x000";
+        assert_eq!(
+            read(dict).unwrap(),
+            Value::Dict(vec![(Value::Str("a".to_owned()), Value::Int(1))])
+        );
+
+        assert!(read(b"i\x01").is_err());
+        assert!(read(b"r\x00\x00\x00\x00").is_err());
+        // Code objects never show up in a profile
+        assert!(read(b"c").is_err());
+    }
+
+    #[test]
+    fn pipeline() {
+        let graph = load_profile(Path::new("assets/profile/pipeline.prof")).unwrap();
+        // Functions are named like the Python frontend names them
+        let run = graph.find("pipeline.Pipeline.run").unwrap();
+        assert_eq!(graph.node(run).kind, NodeKind::Method);
+        assert!(graph.node(run).location.is_some());
+        assert!(graph.node(run).cumulative_time.unwrap() > 0.);
+
+        // Steps are dispatched through `getattr`, which only the profile can follow
+        for step in ["step_clean", "step_scale", "step_sort"] {
+            let callee = format!("pipeline.Pipeline.{step}");
+            let call = edge(&graph, "pipeline.Pipeline.run", &callee);
+            assert_eq!(call.count, Some(100));
+            assert!(call.sites.is_empty() && call.is_unconditional());
+        }
+        assert_eq!(
+            edge(&graph, "pipeline.main", "pipeline.Pipeline.run").weight(),
+            100
+        );
+        edge(&graph, "pipeline.main", "pipeline.Pipeline.__init__");
+
+        let comprehension = "pipeline.Pipeline.step_scale.<locals>.<listcomp@L20>";
+        edge(&graph, "pipeline.Pipeline.step_scale", comprehension);
+        assert_eq!(edge(&graph, comprehension, "pipeline.scale").weight(), 300);
+        let lambda = "pipeline.Pipeline.step_sort.<locals>.<lambda@L23>";
+        assert_eq!(edge(&graph, "sorted", lambda).weight(), 300);
+        let parent = graph.node(graph.find(lambda).unwrap()).parent;
+        assert_eq!(parent, graph.find("pipeline.Pipeline.step_sort"));
+
+        let getattr = edge(&graph, "pipeline.Pipeline.run", "getattr");
+        assert_eq!(graph.node(getattr.callee).category, Category::Builtin);
+        assert!(graph
+            .nodes()
+            .all(|(_, node)| !node.name.contains("_lsprof")));
+    }
+
+    #[test]
+    fn interpreter_functions() {
+        let method = builtin("<method 'append' of 'list' objects>");
+        assert_eq!(
+            (method.name.as_str(), method.category),
+            ("list.append", Category::Builtin)
+        );
+        let function = builtin("<built-in method builtins.len>");
+        assert_eq!(
+            (function.name.as_str(), function.category),
+            ("len", Category::Builtin)
+        );
+        let stdlib = builtin("<built-in method time.perf_counter>");
+        assert_eq!(stdlib.category, Category::Stdlib);
+        let private = builtin("<method 'join' of '_io.TextIOWrapper' objects>");
+        assert_eq!(private.category, Category::Stdlib);
+    }
+
+    #[test]
+    fn disambiguation() {
+        let mut names = Names {
+            profile_dir: PathBuf::new(),
+            graphs: HashMap::new(),
+            named: HashMap::new(),
+        };
+        let pipeline = Path::new("assets/profile/pipeline.py");
+        let functions = [
+            ("missing/shapes.py".to_owned(), 3, "__init__".to_owned()),
+            ("missing/shapes.py".to_owned(), 9, "__init__".to_owned()),
+            (pipeline.display().to_string(), 11, "run".to_owned()),
+            (
+                pipeline.canonicalize().unwrap().display().to_string(),
+                11,
+                "run".to_owned(),
+            ),
+        ];
+        for function in &functions {
+            names.name(function);
+        }
+        names.disambiguate();
+        let name = |i: usize| names.named[&functions[i]].name.as_str();
+        // Files that can't be read only give the module
+        assert_eq!(
+            (name(0), name(1)),
+            ("shapes.__init__:3", "shapes.__init__:9")
+        );
+        // The same definition, through two paths to its file
+        assert_eq!(
+            (name(2), name(3)),
+            ("pipeline.Pipeline.run", "pipeline.Pipeline.run")
+        );
+    }
+}
@@ -0,0 +1,1452 @@
+use proc_macro2::Span;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+use syn::{
+    punctuated::Punctuated,
+    spanned::Spanned,
+    visit::{self, Visit},
+    Block, Expr, ExprCall, ExprClosure, ExprField, ExprForLoop, ExprIf, ExprLoop, ExprMatch,
+    ExprMethodCall, ExprPath, ExprWhile, Fields, FnArg, GenericArgument, Ident, ImplItem, Item,
+    Local, Macro, Member, Pat, PatIdent, PatType, PathArguments, ReturnType, Signature, Stmt,
+    Token, TraitItem, Type, TypePath, UseTree,
+};
+
+use crate::{
+    call_graph::{
+        Ambiguity, CallGraph, CallSite, Category, Context, Edge, EdgeKind, Location, NodeId,
+        NodeKind,
+    },
+    error::GraphError,
+    frontend::{Frontend, GraphOptions},
+};
+
+/// Crates that come with the compiler
+const STD_CRATES: &[&str] = &["alloc", "core", "std"];
+
+/// Names that every module can use without importing them: the standard prelude and the
+/// primitive types. Sorted so they can be binary searched.
+const PRELUDE: &[&str] = &[
+    "Box",
+    "Clone",
+    "Default",
+    "Err",
+    "From",
+    "Into",
+    "IntoIterator",
+    "Iterator",
+    "None",
+    "Ok",
+    "Option",
+    "Result",
+    "Some",
+    "String",
+    "ToOwned",
+    "ToString",
+    "TryFrom",
+    "TryInto",
+    "Vec",
+    "bool",
+    "char",
+    "drop",
+    "f32",
+    "f64",
+    "i128",
+    "i16",
+    "i32",
+    "i64",
+    "i8",
+    "isize",
+    "str",
+    "u128",
+    "u16",
+    "u32",
+    "u64",
+    "u8",
+    "usize",
+];
+
+/// Methods of standard library types and traits, used to guess where calls on values of
+/// unknown types go. Sorted so they can be binary searched.
+const STD_METHODS: &[&str] = &[
+    "all",
+    "and_then",
+    "any",
+    "as_bytes",
+    "as_mut",
+    "as_ref",
+    "as_str",
+    "borrow",
+    "borrow_mut",
+    "chain",
+    "chars",
+    "clear",
+    "clone",
+    "cloned",
+    "collect",
+    "contains",
+    "contains_key",
+    "copied",
+    "count",
+    "drain",
+    "ends_with",
+    "entry",
+    "enumerate",
+    "eq",
+    "expect",
+    "extend",
+    "filter",
+    "filter_map",
+    "find",
+    "first",
+    "flat_map",
+    "flatten",
+    "fold",
+    "for_each",
+    "get",
+    "get_mut",
+    "insert",
+    "into_iter",
+    "is_empty",
+    "is_err",
+    "is_none",
+    "is_ok",
+    "is_some",
+    "iter",
+    "iter_mut",
+    "join",
+    "keys",
+    "last",
+    "len",
+    "lines",
+    "lock",
+    "map",
+    "map_err",
+    "max",
+    "min",
+    "next",
+    "ok",
+    "ok_or",
+    "or_insert",
+    "or_insert_with",
+    "parse",
+    "peekable",
+    "pop",
+    "position",
+    "push",
+    "push_str",
+    "remove",
+    "replace",
+    "retain",
+    "rev",
+    "skip",
+    "sort",
+    "sort_by",
+    "split",
+    "starts_with",
+    "sum",
+    "take",
+    "to_lowercase",
+    "to_owned",
+    "to_string",
+    "to_uppercase",
+    "to_vec",
+    "trim",
+    "unwrap",
+    "unwrap_or",
+    "unwrap_or_default",
+    "unwrap_or_else",
+    "values",
+    "windows",
+    "zip",
+];
+
+/// The Rust frontend. Nodes are named by crate and module path, e.g. `app::db::connect`, and
+/// methods by the path of their type or trait, e.g. `app::db::Pool::get`.
+pub struct Rust;
+
+impl Frontend for Rust {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["rs"]
+    }
+
+    fn generate(
+        &self,
+        sources: Vec<(PathBuf, String)>,
+        _base: Option<&Path>,
+        options: &GraphOptions,
+    ) -> Result<CallGraph, GraphError> {
+        generate_graph_from_sources(sources, options)
+    }
+}
+
+/// A file that was parsed, before its calls are walked.
+struct ParsedFile {
+    path: PathBuf,
+    /// Path of the module the file holds
+    module: String,
+    file: syn::File,
+}
+
+fn generate_graph_from_sources(
+    sources: Vec<(PathBuf, String)>,
+    options: &GraphOptions,
+) -> Result<CallGraph, GraphError> {
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+    for (path, src) in sources {
+        let file = match syn::parse_file(&src) {
+            Ok(file) => file,
+            Err(error) => {
+                let start = error.span().start();
+                let error = GraphError::Syntax {
+                    file: path,
+                    line: start.line,
+                    column: start.column + 1,
+                    message: error.to_string(),
+                };
+                if options.lenient {
+                    errors.push(error);
+                    continue;
+                }
+                return Err(error);
+            }
+        };
+        files.push(ParsedFile {
+            module: module_path(&path),
+            path,
+            file,
+        });
+    }
+
+    // Items can be used before they are defined, and from any file
+    let mut definitions = Definitions::default();
+    for parsed in &files {
+        collect_items(&parsed.file.items, &parsed.module, &mut definitions);
+    }
+    for parsed in &files {
+        collect_definitions(&parsed.file.items, &parsed.module, None, &mut definitions);
+    }
+
+    let mut graph = CallGraph::default();
+    for parsed in &files {
+        let module = Module {
+            name: &parsed.module,
+            path: &parsed.path,
+            definitions: &definitions,
+        };
+        build_graph_from_items(&parsed.file.items, &module, None, &mut graph);
+    }
+
+    add_dispatches(&mut graph, &definitions);
+    graph.merge_edges();
+    graph.errors = errors;
+
+    Ok(graph)
+}
+
+/// The crate and module path of a file, e.g. `app::db::pool` for `app/src/db/pool.rs`. Files
+/// outside of a package's `src` directory, like tests and examples, are crates of their own.
+fn module_path(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().replace('-', "_"))
+        .unwrap_or_default();
+    let Some(package) = path
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.join("Cargo.toml").is_file())
+    else {
+        return stem;
+    };
+    let Ok(relative) = path.strip_prefix(package.join("src")) else {
+        return stem;
+    };
+
+    let mut parts = vec![crate_name(package)];
+    let dirs = relative.parent().into_iter().flat_map(Path::iter);
+    parts.extend(dirs.map(|dir| dir.to_string_lossy().into_owned()));
+    let is_root = parts.len() == 1 && (stem == "lib" || stem == "main");
+    if stem != "mod" && !is_root {
+        parts.push(stem);
+    }
+    parts.join("::")
+}
+
+/// The name of the package in `package`, as it's written in paths.
+fn crate_name(package: &Path) -> String {
+    let manifest = std::fs::read_to_string(package.join("Cargo.toml")).unwrap_or_default();
+    let mut in_package = false;
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if let Some((key, value)) = line.split_once('=').filter(|_| in_package) {
+            if key.trim() == "name" {
+                return value.trim().trim_matches('"').replace('-', "_");
+            }
+        }
+    }
+    package
+        .file_name()
+        .map(|name| name.to_string_lossy().replace('-', "_"))
+        .unwrap_or_default()
+}
+
+/// What is defined anywhere in the analyzed code, collected before any calls are resolved.
+#[derive(Default)]
+struct Definitions {
+    /// Every module, including inline `mod` blocks, by path
+    modules: HashMap<String, ModuleItems>,
+    /// Free functions, including ones nested in other functions
+    functions: HashSet<String>,
+    /// Methods as `Type::method`, including trait methods as `Trait::method`
+    methods: HashSet<String>,
+    /// The unqualified names in `methods`
+    method_names: HashSet<String>,
+    /// The traits each type implements
+    traits: HashMap<String, Vec<String>>,
+    /// Types and traits that have methods, which get a node of their own
+    owners: HashSet<String>,
+    /// The types functions return, when they are written as a path
+    returns: HashMap<String, String>,
+    /// The types of named struct fields, as `Type::field`
+    fields: HashMap<String, String>,
+}
+
+/// The names a module can use without a path.
+#[derive(Default)]
+struct ModuleItems {
+    /// Names of the items defined in the module
+    items: HashSet<String>,
+    /// Names brought in by `use`, and the paths they stand for as written
+    uses: HashMap<String, Vec<String>>,
+    /// Paths of glob imports as written
+    globs: Vec<Vec<String>>,
+}
+
+impl Definitions {
+    /// Resolves a path as written in `module` to the path of what it names. Paths that leave
+    /// the analyzed code, e.g. into `std`, are returned as written.
+    fn resolve(&self, module: &str, segments: &[String]) -> String {
+        self.resolve_at(module, segments, 0)
+    }
+
+    fn resolve_at(&self, module: &str, segments: &[String], depth: usize) -> String {
+        let Some((first, rest)) = segments.split_first() else {
+            return module.to_owned();
+        };
+        let mut resolved = match first.as_str() {
+            "crate" => crate_of(module).to_owned(),
+            "self" => module.to_owned(),
+            "super" => parent_of(module).to_owned(),
+            _ => match self.lookup(module, first, depth) {
+                Some(path) => path,
+                // Another analyzed crate
+                None if self.modules.contains_key(first) => first.clone(),
+                None => return segments.join("::"),
+            },
+        };
+        for segment in rest {
+            resolved = match segment.as_str() {
+                "super" => parent_of(&resolved).to_owned(),
+                _ => self
+                    .lookup(&resolved, segment, depth)
+                    .unwrap_or_else(|| format!("{resolved}::{segment}")),
+            };
+        }
+        resolved
+    }
+
+    /// Finds what `name` stands for in `module`, through its items and imports.
+    fn lookup(&self, module: &str, name: &str, depth: usize) -> Option<String> {
+        // Re-exports can refer to each other in a cycle
+        if depth > 16 {
+            return None;
+        }
+        let items = self.modules.get(module)?;
+        if items.items.contains(name) {
+            return Some(format!("{module}::{name}"));
+        }
+        if let Some(target) = items.uses.get(name) {
+            return Some(self.resolve_at(module, target, depth + 1));
+        }
+        items.globs.iter().find_map(|glob| {
+            let glob = self.resolve_at(module, glob, depth + 1);
+            self.lookup(&glob, name, depth + 1)
+        })
+    }
+
+    /// The path of a type written in `module`, seeing through references and smart pointers,
+    /// since methods are called through them.
+    fn type_path(&self, module: &str, ty: &Type, self_ty: Option<&str>) -> Option<String> {
+        match ty {
+            Type::Reference(reference) => self.type_path(module, &reference.elem, self_ty),
+            Type::Paren(paren) => self.type_path(module, &paren.elem, self_ty),
+            Type::Group(group) => self.type_path(module, &group.elem, self_ty),
+            Type::Path(TypePath { qself: None, path }) => {
+                let last = path.segments.last()?;
+                if matches!(last.ident.to_string().as_str(), "Box" | "Rc" | "Arc") {
+                    if let PathArguments::AngleBracketed(args) = &last.arguments {
+                        if let Some(GenericArgument::Type(inner)) = args.args.first() {
+                            return self.type_path(module, inner, self_ty);
+                        }
+                    }
+                }
+                let segments = path_segments(path);
+                match segments.as_slice() {
+                    [name] if name == "Self" => self_ty.map(str::to_owned),
+                    _ => Some(self.resolve(module, &segments)),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Finds the method a call to `method` on a value of type `ty` goes to: one defined for
+    /// the type itself, or one that a trait it implements provides.
+    fn find_method(&self, ty: &str, method: &str) -> Option<String> {
+        let own = format!("{ty}::{method}");
+        if self.methods.contains(&own) {
+            return Some(own);
+        }
+        self.traits
+            .get(ty)?
+            .iter()
+            .map(|trait_| format!("{trait_}::{method}"))
+            .find(|method| self.methods.contains(method))
+    }
+
+    fn is_defined(&self, path: &str) -> bool {
+        self.functions.contains(path) || self.methods.contains(path)
+    }
+
+    /// Where something that isn't defined in the analyzed code comes from, going by the
+    /// crate its path starts with.
+    fn category(&self, path: &str) -> Category {
+        let root = path.split("::").next().unwrap_or(path);
+        if self.modules.contains_key(root) {
+            Category::Project
+        } else if STD_CRATES.contains(&root) {
+            Category::Stdlib
+        } else if PRELUDE.binary_search(&root).is_ok() {
+            Category::Builtin
+        } else if path.contains("::") {
+            Category::ThirdParty
+        } else {
+            Category::Project
+        }
+    }
+}
+
+fn crate_of(module: &str) -> &str {
+    module.split("::").next().unwrap_or(module)
+}
+
+fn parent_of(module: &str) -> &str {
+    module
+        .rsplit_once("::")
+        .map_or(module, |(parent, _)| parent)
+}
+
+fn path_segments(path: &syn::Path) -> Vec<String> {
+    path.segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect()
+}
+
+/// Collects the names each module defines and imports, including inline modules.
+fn collect_items(items: &[Item], module: &str, definitions: &mut Definitions) {
+    let mut module_items = ModuleItems::default();
+    for item in items {
+        let name = match item {
+            Item::Fn(item) => &item.sig.ident,
+            Item::Struct(item) => &item.ident,
+            Item::Enum(item) => &item.ident,
+            Item::Union(item) => &item.ident,
+            Item::Trait(item) => &item.ident,
+            Item::TraitAlias(item) => &item.ident,
+            Item::Type(item) => &item.ident,
+            Item::Const(item) => &item.ident,
+            Item::Static(item) => &item.ident,
+            Item::Mod(item) => {
+                if let Some((_, items)) = &item.content {
+                    collect_items(items, &format!("{module}::{}", item.ident), definitions);
+                }
+                &item.ident
+            }
+            Item::Use(item) => {
+                collect_use(&item.tree, Vec::new(), &mut module_items);
+                continue;
+            }
+            Item::ExternCrate(item) => {
+                let name = item
+                    .rename
+                    .as_ref()
+                    .map_or(&item.ident, |(_, rename)| rename);
+                module_items
+                    .uses
+                    .insert(name.to_string(), vec![item.ident.to_string()]);
+                continue;
+            }
+            _ => continue,
+        };
+        module_items.items.insert(name.to_string());
+    }
+    definitions.modules.insert(module.to_owned(), module_items);
+}
+
+fn collect_use(tree: &UseTree, mut prefix: Vec<String>, items: &mut ModuleItems) {
+    match tree {
+        UseTree::Path(path) => {
+            prefix.push(path.ident.to_string());
+            collect_use(&path.tree, prefix, items);
+        }
+        // `use a::b::{self}` imports `b`
+        UseTree::Name(name) if name.ident == "self" => {
+            if let Some(last) = prefix.last() {
+                items.uses.insert(last.clone(), prefix.clone());
+            }
+        }
+        UseTree::Name(name) => {
+            prefix.push(name.ident.to_string());
+            items.uses.insert(name.ident.to_string(), prefix);
+        }
+        UseTree::Rename(rename) => {
+            // `use Trait as _` only brings the trait's methods into scope
+            if rename.rename == "_" {
+                return;
+            }
+            if rename.ident != "self" {
+                prefix.push(rename.ident.to_string());
+            }
+            items.uses.insert(rename.rename.to_string(), prefix);
+        }
+        UseTree::Glob(_) => items.globs.push(prefix),
+        UseTree::Group(group) => {
+            for tree in &group.items {
+                collect_use(tree, prefix.clone(), items);
+            }
+        }
+    }
+}
+
+/// Collects functions, methods, trait implementations and the types of return values and
+/// fields. `function` is the function the items are nested in, if any.
+fn collect_definitions(
+    items: &[Item],
+    module: &str,
+    function: Option<&str>,
+    definitions: &mut Definitions,
+) {
+    for item in items {
+        match item {
+            Item::Fn(item) => {
+                let name = format!("{}::{}", function.unwrap_or(module), item.sig.ident);
+                collect_function(
+                    &name,
+                    &item.sig,
+                    Some(&item.block),
+                    module,
+                    None,
+                    definitions,
+                );
+                definitions.functions.insert(name);
+            }
+            Item::Impl(item) => {
+                let Some(ty) = definitions.type_path(module, &item.self_ty, None) else {
+                    continue;
+                };
+                if let Some((_, trait_, _)) = &item.trait_ {
+                    let trait_ = definitions.resolve(module, &path_segments(trait_));
+                    definitions
+                        .traits
+                        .entry(ty.clone())
+                        .or_default()
+                        .push(trait_);
+                }
+                for item in &item.items {
+                    let ImplItem::Fn(method) = item else {
+                        continue;
+                    };
+                    let name = format!("{ty}::{}", method.sig.ident);
+                    let block = Some(&method.block);
+                    collect_function(&name, &method.sig, block, module, Some(&ty), definitions);
+                    definitions.add_method(&ty, name);
+                }
+            }
+            Item::Trait(item) => {
+                let trait_ = format!("{module}::{}", item.ident);
+                for item in &item.items {
+                    let TraitItem::Fn(method) = item else {
+                        continue;
+                    };
+                    let name = format!("{trait_}::{}", method.sig.ident);
+                    let block = method.default.as_ref();
+                    // Calls on `self` go to the trait's methods, which dispatch to the
+                    // implementations
+                    let self_ty = Some(trait_.as_str());
+                    collect_function(&name, &method.sig, block, module, self_ty, definitions);
+                    definitions.add_method(&trait_, name);
+                }
+            }
+            Item::Struct(item) => {
+                let Fields::Named(fields) = &item.fields else {
+                    continue;
+                };
+                let ty = format!("{module}::{}", item.ident);
+                for field in &fields.named {
+                    let (Some(ident), Some(field_ty)) = (
+                        &field.ident,
+                        definitions.type_path(module, &field.ty, Some(&ty)),
+                    ) else {
+                        continue;
+                    };
+                    definitions
+                        .fields
+                        .insert(format!("{ty}::{ident}"), field_ty);
+                }
+            }
+            Item::Mod(item) => {
+                if let Some((_, items)) = &item.content {
+                    let module = format!("{module}::{}", item.ident);
+                    collect_definitions(items, &module, None, definitions);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Collects the return type of a function and the functions nested in its body.
+fn collect_function(
+    name: &str,
+    sig: &Signature,
+    block: Option<&Block>,
+    module: &str,
+    self_ty: Option<&str>,
+    definitions: &mut Definitions,
+) {
+    if let ReturnType::Type(_, ty) = &sig.output {
+        if let Some(ty) = definitions.type_path(module, ty, self_ty) {
+            definitions.returns.insert(name.to_owned(), ty);
+        }
+    }
+    if let Some(block) = block {
+        collect_definitions(&nested_items(block), module, Some(name), definitions);
+    }
+}
+
+impl Definitions {
+    fn add_method(&mut self, owner: &str, method: String) {
+        if let Some((_, name)) = method.rsplit_once("::") {
+            self.method_names.insert(name.to_owned());
+        }
+        self.owners.insert(owner.to_owned());
+        self.methods.insert(method);
+    }
+}
+
+/// The items defined directly in a function's body.
+fn nested_items(block: &Block) -> Vec<Item> {
+    block
+        .stmts
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Stmt::Item(item) => Some(item.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A file being walked.
+struct Module<'a> {
+    /// Path of the module, e.g. `app::db`
+    name: &'a str,
+    path: &'a Path,
+    definitions: &'a Definitions,
+}
+
+impl Module<'_> {
+    fn location(&self, start: Span, end: Span) -> Location {
+        Location {
+            file: self.path.to_path_buf(),
+            start_line: start.start().line,
+            end_line: end.end().line,
+        }
+    }
+
+    fn call_site(&self, receiver: Option<String>, span: Span, context: Context) -> CallSite {
+        let start = span.start();
+        CallSite {
+            receiver,
+            line: start.line,
+            column: start.column + 1,
+            context,
+        }
+    }
+}
+
+/// The function or closure that calls are currently attributed to.
+#[derive(Clone)]
+struct Scope {
+    name: String,
+    /// The type `self` and `Self` stand for
+    self_ty: Option<String>,
+    /// Functions defined in the bodies of this and the enclosing functions, by name
+    locals: HashMap<String, String>,
+    /// Variables bound in this function's body, including its parameters
+    variables: HashMap<String, Variable>,
+}
+
+impl Scope {
+    /// Resolves the path of a function or type used in this scope.
+    fn resolve(&self, module: &Module, path: &syn::Path) -> String {
+        let segments = path_segments(path);
+        match segments.as_slice() {
+            [first, rest @ ..] if first == "Self" => {
+                let ty = self.self_ty.clone().unwrap_or_else(|| first.clone());
+                [ty].iter()
+                    .chain(rest)
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join("::")
+            }
+            [name] => match self.locals.get(name) {
+                Some(local) => local.clone(),
+                None => {
+                    let resolved = module.definitions.resolve(module.name, &segments);
+                    // Unknown names are most likely defined by a macro in the same module
+                    if resolved == *name && PRELUDE.binary_search(&name.as_str()).is_err() {
+                        format!("{}::{name}", module.name)
+                    } else {
+                        resolved
+                    }
+                }
+            },
+            _ => module.definitions.resolve(module.name, &segments),
+        }
+    }
+
+    fn variable(&self, expr: &Expr) -> Option<&Variable> {
+        let Expr::Path(ExprPath {
+            path, qself: None, ..
+        }) = expr
+        else {
+            return None;
+        };
+        self.variables.get(&path.get_ident()?.to_string())
+    }
+
+    /// The type of an expression, when it's obvious without looking at other functions'
+    /// bodies.
+    fn expr_type(&self, module: &Module, expr: &Expr) -> Option<String> {
+        let definitions = module.definitions;
+        match expr {
+            Expr::Path(ExprPath { path, .. }) if path.is_ident("self") => self.self_ty.clone(),
+            Expr::Path(_) => self.variable(expr)?.ty().map(str::to_owned),
+            Expr::Field(ExprField { base, member, .. }) => {
+                let Member::Named(field) = member else {
+                    return None;
+                };
+                let base = self.expr_type(module, base)?;
+                definitions.fields.get(&format!("{base}::{field}")).cloned()
+            }
+            Expr::Struct(expr) => Some(self.resolve(module, &expr.path)),
+            Expr::Call(ExprCall { func, .. }) => {
+                let Expr::Path(ExprPath { path, .. }) = &**func else {
+                    return None;
+                };
+                let function = self.resolve(module, path);
+                definitions.returns.get(&function).cloned()
+            }
+            Expr::MethodCall(call) => {
+                let ty = self.expr_type(module, &call.receiver)?;
+                let method = definitions.find_method(&ty, &call.method.to_string())?;
+                definitions.returns.get(&method).cloned()
+            }
+            Expr::Reference(reference) => self.expr_type(module, &reference.expr),
+            Expr::Paren(paren) => self.expr_type(module, &paren.expr),
+            _ => None,
+        }
+    }
+}
+
+/// The types a variable is bound to anywhere in its function.
+#[derive(Default, Clone)]
+struct Variable {
+    types: Vec<String>,
+    /// Whether it's also bound to something of an unknown type
+    unknown: bool,
+}
+
+impl Variable {
+    fn assign(&mut self, ty: Option<String>) {
+        match ty {
+            Some(ty) if !self.types.contains(&ty) => self.types.push(ty),
+            Some(_) => {}
+            None => self.unknown = true,
+        }
+    }
+
+    /// The variable's type, if it only ever has one.
+    fn ty(&self) -> Option<&str> {
+        match self.types.as_slice() {
+            [ty] if !self.unknown => Some(ty),
+            _ => None,
+        }
+    }
+}
+
+/// Finds the variables bound in a function body. Shadowing is flow-insensitive: a name that
+/// is bound to different types anywhere in the body has all of them.
+struct Bindings<'a> {
+    scope: &'a Scope,
+    module: &'a Module<'a>,
+    variables: HashMap<String, Variable>,
+}
+
+impl<'ast> Visit<'ast> for Bindings<'_> {
+    fn visit_local(&mut self, local: &'ast Local) {
+        let (ident, ty) = match &local.pat {
+            Pat::Ident(pat) if pat.subpat.is_none() => (Some(&pat.ident), None),
+            Pat::Type(PatType { pat, ty, .. }) => match &**pat {
+                Pat::Ident(pat) if pat.subpat.is_none() => (Some(&pat.ident), Some(ty)),
+                _ => (None, None),
+            },
+            _ => (None, None),
+        };
+        match ident {
+            Some(ident) => {
+                let ty = match ty {
+                    Some(ty) => {
+                        let self_ty = self.scope.self_ty.as_deref();
+                        self.module
+                            .definitions
+                            .type_path(self.module.name, ty, self_ty)
+                    }
+                    None => local
+                        .init
+                        .as_ref()
+                        .and_then(|init| self.scope.expr_type(self.module, &init.expr)),
+                };
+                self.variables
+                    .entry(ident.to_string())
+                    .or_default()
+                    .assign(ty);
+            }
+            None => self.visit_pat(&local.pat),
+        }
+        if let Some(init) = &local.init {
+            self.visit_local_init(init);
+        }
+    }
+
+    // Bindings in patterns, e.g. in `if let` or `match`, could be anything
+    fn visit_pat_ident(&mut self, pat: &'ast PatIdent) {
+        self.variables
+            .entry(pat.ident.to_string())
+            .or_default()
+            .assign(None);
+        visit::visit_pat_ident(self, pat);
+    }
+
+    fn visit_item(&mut self, _: &'ast Item) {}
+}
+
+/// Walks items, defining nodes for the functions, methods and types among them. `parent` is
+/// the function they are nested in, if any.
+fn build_graph_from_items(
+    items: &[Item],
+    module: &Module,
+    parent: Option<(&Scope, NodeId)>,
+    graph: &mut CallGraph,
+) {
+    let definitions = module.definitions;
+    for item in items {
+        match item {
+            Item::Fn(item) => {
+                let prefix = parent.map_or(module.name, |(scope, _)| &scope.name);
+                let name = format!("{prefix}::{}", item.sig.ident);
+                let location = module.location(item.sig.fn_token.span, item.span());
+                let id = graph.define(&name, NodeKind::Function, location, parent.map(|p| p.1));
+                let locals = parent.map(|(scope, _)| scope.locals.clone());
+                build_graph_from_fn(
+                    id,
+                    name,
+                    &item.sig,
+                    &item.block,
+                    None,
+                    locals,
+                    module,
+                    graph,
+                );
+            }
+            Item::Impl(item) => {
+                let Some(ty) = definitions.type_path(module.name, &item.self_ty, None) else {
+                    continue;
+                };
+                let owner = (definitions.category(&ty) == Category::Project)
+                    .then(|| graph.add_node(&ty, NodeKind::Class, Category::Project));
+                for item in &item.items {
+                    let ImplItem::Fn(method) = item else {
+                        continue;
+                    };
+                    let name = format!("{ty}::{}", method.sig.ident);
+                    let location = module.location(method.sig.fn_token.span, method.span());
+                    let id = graph.define(&name, NodeKind::Method, location, owner);
+                    let (sig, block) = (&method.sig, &method.block);
+                    let self_ty = Some(ty.clone());
+                    build_graph_from_fn(id, name, sig, block, self_ty, None, module, graph);
+                }
+            }
+            Item::Trait(item) => {
+                let trait_ = format!("{}::{}", module.name, item.ident);
+                let location = module.location(item.trait_token.span, item.span());
+                let owner = graph.define(&trait_, NodeKind::Class, location, None);
+                for item in &item.items {
+                    let TraitItem::Fn(method) = item else {
+                        continue;
+                    };
+                    let name = format!("{trait_}::{}", method.sig.ident);
+                    let location = module.location(method.sig.fn_token.span, method.span());
+                    let id = graph.define(&name, NodeKind::Method, location, Some(owner));
+                    if let Some(block) = &method.default {
+                        let sig = &method.sig;
+                        let self_ty = Some(trait_.clone());
+                        build_graph_from_fn(id, name, sig, block, self_ty, None, module, graph);
+                    }
+                }
+            }
+            Item::Struct(syn::ItemStruct { ident, .. })
+            | Item::Enum(syn::ItemEnum { ident, .. })
+            | Item::Union(syn::ItemUnion { ident, .. }) => {
+                // Only types with methods are worth a node
+                let ty = format!("{}::{ident}", module.name);
+                if definitions.owners.contains(&ty) {
+                    let location = module.location(ident.span(), item.span());
+                    graph.define(&ty, NodeKind::Class, location, None);
+                }
+            }
+            Item::Mod(item) => {
+                if let Some((_, items)) = &item.content {
+                    let name = format!("{}::{}", module.name, item.ident);
+                    let inner = Module {
+                        name: &name,
+                        ..*module
+                    };
+                    build_graph_from_items(items, &inner, None, graph);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_graph_from_fn(
+    id: NodeId,
+    name: String,
+    sig: &Signature,
+    block: &Block,
+    self_ty: Option<String>,
+    locals: Option<HashMap<String, String>>,
+    module: &Module,
+    graph: &mut CallGraph,
+) {
+    let nested = nested_items(block);
+    let mut locals = locals.unwrap_or_default();
+    for item in &nested {
+        if let Item::Fn(item) = item {
+            let ident = item.sig.ident.to_string();
+            locals.insert(ident.clone(), format!("{name}::{ident}"));
+        }
+    }
+    let mut scope = Scope {
+        name,
+        self_ty,
+        locals,
+        variables: HashMap::new(),
+    };
+
+    for input in &sig.inputs {
+        let FnArg::Typed(PatType { pat, ty, .. }) = input else {
+            continue;
+        };
+        let Pat::Ident(pat) = &**pat else {
+            continue;
+        };
+        let ty = module
+            .definitions
+            .type_path(module.name, ty, scope.self_ty.as_deref());
+        scope
+            .variables
+            .entry(pat.ident.to_string())
+            .or_default()
+            .assign(ty);
+    }
+    let mut bindings = Bindings {
+        scope: &scope,
+        module,
+        variables: scope.variables.clone(),
+    };
+    bindings.visit_block(block);
+    scope.variables = bindings.variables;
+
+    build_graph_from_items(&nested, module, Some((&scope, id)), graph);
+    Walker {
+        module,
+        scope: &scope,
+        caller: id,
+        context: Context::default(),
+        graph,
+    }
+    .visit_block(block);
+}
+
+/// Adds the calls made in a function's body to the graph.
+struct Walker<'a> {
+    module: &'a Module<'a>,
+    scope: &'a Scope,
+    caller: NodeId,
+    context: Context,
+    graph: &'a mut CallGraph,
+}
+
+impl Walker<'_> {
+    fn add_call(
+        &mut self,
+        callee: &str,
+        category: Category,
+        kind: EdgeKind,
+        receiver: Option<String>,
+        span: Span,
+        candidates: Vec<String>,
+    ) {
+        // Until a definition turns up, callees are only known by where they come from
+        let node_kind = match category {
+            Category::Builtin => NodeKind::Builtin,
+            Category::Stdlib | Category::ThirdParty => NodeKind::External,
+            Category::Project => NodeKind::Function,
+        };
+        let callee = self.graph.add_node(callee, node_kind, category);
+        let site = self.module.call_site(receiver, span, self.context);
+        if !candidates.is_empty() {
+            self.graph.ambiguities.push(Ambiguity {
+                caller: self.caller,
+                call: self.graph.node(callee).name.clone(),
+                candidates,
+                site: site.clone(),
+            });
+        }
+        self.graph.edges.push(Edge {
+            caller: self.caller,
+            callee,
+            kind,
+            sites: vec![site],
+            count: None,
+        });
+    }
+
+    /// A call through a path, e.g. `load()`, `Pool::new()` or `Self::check()`.
+    fn path_call(&mut self, path: &syn::Path) {
+        // Tuple structs and enum variants are called like functions, but only build a value
+        let Some(last) = path.segments.last() else {
+            return;
+        };
+        if last.ident.to_string().starts_with(char::is_uppercase) {
+            return;
+        }
+        let definitions = self.module.definitions;
+        let mut callee = self.scope.resolve(self.module, path);
+        // `Type::method()` can go to a method that a trait provides
+        if let Some((ty, method)) = callee.rsplit_once("::") {
+            if let Some(method) = definitions.find_method(ty, method) {
+                callee = method;
+            }
+        }
+        let category = if definitions.is_defined(&callee) {
+            Category::Project
+        } else {
+            definitions.category(&callee)
+        };
+        let span = path.span();
+        self.add_call(&callee, category, EdgeKind::Call, None, span, Vec::new());
+    }
+
+    /// A call through a value, e.g. `self.load()` or `pool.get()`.
+    fn method_call(&mut self, receiver: &Expr, method: &Ident) {
+        let definitions = self.module.definitions;
+        let name = method.to_string();
+        let chain = receiver_chain(receiver);
+        let span = method.span();
+        if let Some(ty) = self.scope.expr_type(self.module, receiver) {
+            let callee = definitions
+                .find_method(&ty, &name)
+                .unwrap_or_else(|| format!("{ty}::{name}"));
+            let category = if definitions.is_defined(&callee) {
+                Category::Project
+            } else {
+                definitions.category(&ty)
+            };
+            let kind = EdgeKind::AttributeCall;
+            self.add_call(&callee, category, kind, Some(chain), span, Vec::new());
+            return;
+        }
+
+        // Guessing one of the types would hide that the others are possible
+        let candidates = self
+            .scope
+            .variable(receiver)
+            .filter(|variable| !variable.unknown)
+            .map(|variable| {
+                variable
+                    .types
+                    .iter()
+                    .map(|ty| {
+                        definitions
+                            .find_method(ty, &name)
+                            .unwrap_or_else(|| format!("{ty}::{name}"))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        // The receiver's type isn't known, so this is only a guess. A method the project
+        // defines is more likely than a standard one with the same name
+        let category = if !definitions.method_names.contains(&name)
+            && STD_METHODS.binary_search(&name.as_str()).is_ok()
+        {
+            Category::Builtin
+        } else {
+            Category::Project
+        };
+        // Receivers like `self.items` mean something else in every type, and `<expr>` in every
+        // function, so they're kept apart by where they are used
+        let owner = match &self.scope.self_ty {
+            Some(ty) if !chain.contains("<expr>") => ty,
+            _ => &self.scope.name,
+        };
+        let callee = match chain.as_str() {
+            "<expr>" | "<literal>" => format!("{owner}::{chain}::{name}"),
+            _ => format!("{owner}::<{chain}>::{name}"),
+        };
+        let kind = EdgeKind::AttributeCall;
+        self.add_call(&callee, category, kind, Some(chain), span, candidates);
+    }
+
+    fn with_context(&mut self, context: Context, walk: impl FnOnce(&mut Self)) {
+        let outer = std::mem::replace(&mut self.context, context);
+        walk(self);
+        self.context = outer;
+    }
+}
+
+impl<'ast> Visit<'ast> for Walker<'_> {
+    // Nested items are walked with their own scope
+    fn visit_item(&mut self, _: &'ast Item) {}
+
+    fn visit_expr_call(&mut self, call: &'ast ExprCall) {
+        match &*call.func {
+            Expr::Path(ExprPath {
+                path, qself: None, ..
+            }) if self.scope.variable(&call.func).is_none() => self.path_call(path),
+            func => self.visit_expr(func),
+        }
+        for arg in &call.args {
+            self.visit_expr(arg);
+        }
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast ExprMethodCall) {
+        self.visit_expr(&call.receiver);
+        self.method_call(&call.receiver, &call.method);
+        for arg in &call.args {
+            self.visit_expr(arg);
+        }
+    }
+
+    // Functions that are passed around without being called, e.g. `.map(Self::parse)`
+    fn visit_expr_path(&mut self, expr: &'ast ExprPath) {
+        if expr.qself.is_some() || self.scope.variable(&Expr::Path(expr.clone())).is_some() {
+            return;
+        }
+        let callee = self.scope.resolve(self.module, &expr.path);
+        if self.module.definitions.is_defined(&callee) {
+            let (kind, span) = (EdgeKind::Reference, expr.span());
+            self.add_call(&callee, Category::Project, kind, None, span, Vec::new());
+        }
+    }
+
+    fn visit_expr_closure(&mut self, closure: &'ast ExprClosure) {
+        let start = closure.span().start();
+        let mut name = format!("{}::<closure@L{}>", self.scope.name, start.line);
+        if self.graph.find(&name).is_some() {
+            name = format!(
+                "{}::<closure@L{}:{}>",
+                self.scope.name,
+                start.line,
+                start.column + 1
+            );
+        }
+        let location = self.module.location(closure.span(), closure.span());
+        let id = self
+            .graph
+            .define(&name, NodeKind::Lambda, location, Some(self.caller));
+        let span = closure.span();
+        self.add_call(
+            &name,
+            Category::Project,
+            EdgeKind::Reference,
+            None,
+            span,
+            Vec::new(),
+        );
+
+        // Closures see the variables around them, apart from the ones their parameters shadow
+        let mut scope = Scope {
+            name,
+            ..self.scope.clone()
+        };
+        let mut bindings = Bindings {
+            scope: self.scope,
+            module: self.module,
+            variables: HashMap::new(),
+        };
+        for input in &closure.inputs {
+            bindings.visit_pat(input);
+        }
+        scope.variables.extend(bindings.variables);
+        Walker {
+            module: self.module,
+            scope: &scope,
+            caller: id,
+            context: Context::default(),
+            graph: self.graph,
+        }
+        .visit_expr(&closure.body);
+    }
+
+    // Macro arguments are only tokens to the parser. Most macros that are called like
+    // functions, e.g. `println!` or `assert_eq!`, take expressions
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        let parser = Punctuated::<Expr, Token![,]>::parse_terminated;
+        if let Ok(args) = mac.parse_body_with(parser) {
+            for arg in &args {
+                self.visit_expr(arg);
+            }
+        }
+    }
+
+    fn visit_expr_if(&mut self, expr: &'ast ExprIf) {
+        self.visit_expr(&expr.cond);
+        let branch = Context {
+            conditional: true,
+            ..self.context
+        };
+        self.with_context(branch, |walker| {
+            walker.visit_block(&expr.then_branch);
+            if let Some((_, else_branch)) = &expr.else_branch {
+                walker.visit_expr(else_branch);
+            }
+        });
+    }
+
+    fn visit_expr_match(&mut self, expr: &'ast ExprMatch) {
+        self.visit_expr(&expr.expr);
+        let arm = Context {
+            conditional: true,
+            ..self.context
+        };
+        self.with_context(arm, |walker| {
+            for arm in &expr.arms {
+                if let Some((_, guard)) = &arm.guard {
+                    walker.visit_expr(guard);
+                }
+                walker.visit_expr(&arm.body);
+            }
+        });
+    }
+
+    fn visit_expr_for_loop(&mut self, expr: &'ast ExprForLoop) {
+        self.visit_expr(&expr.expr);
+        // The body may run any number of times, including none
+        let body = Context {
+            in_loop: true,
+            conditional: true,
+            ..self.context
+        };
+        self.with_context(body, |walker| walker.visit_block(&expr.body));
+    }
+
+    fn visit_expr_while(&mut self, expr: &'ast ExprWhile) {
+        let test = Context {
+            in_loop: true,
+            ..self.context
+        };
+        self.with_context(test, |walker| walker.visit_expr(&expr.cond));
+        let body = Context {
+            conditional: true,
+            ..test
+        };
+        self.with_context(body, |walker| walker.visit_block(&expr.body));
+    }
+
+    fn visit_expr_loop(&mut self, expr: &'ast ExprLoop) {
+        let body = Context {
+            in_loop: true,
+            ..self.context
+        };
+        self.with_context(body, |walker| walker.visit_block(&expr.body));
+    }
+}
+
+/// Renders the receiver of a method call as a dotted chain, e.g. `self.db` or `pool`. Parts
+/// that aren't plain names are abbreviated, so `load().save()` has the receiver `load()`.
+fn receiver_chain(expr: &Expr) -> String {
+    match expr {
+        Expr::Path(ExprPath { path, .. }) => path_segments(path).join("::"),
+        Expr::Field(ExprField { base, member, .. }) => match member {
+            Member::Named(field) => format!("{}.{field}", receiver_chain(base)),
+            Member::Unnamed(index) => format!("{}.{}", receiver_chain(base), index.index),
+        },
+        Expr::Call(ExprCall { func, .. }) => format!("{}()", receiver_chain(func)),
+        Expr::MethodCall(call) => format!("{}.{}()", receiver_chain(&call.receiver), call.method),
+        Expr::Index(index) => format!("{}[]", receiver_chain(&index.expr)),
+        Expr::Reference(reference) => receiver_chain(&reference.expr),
+        Expr::Paren(paren) => receiver_chain(&paren.expr),
+        Expr::Try(expr) => format!("{}?", receiver_chain(&expr.expr)),
+        Expr::Lit(_) => "<literal>".to_owned(),
+        _ => "<expr>".to_owned(),
+    }
+}
+
+/// Adds an edge from every trait method to its implementations, since a call to the trait
+/// method may end up in any of them.
+fn add_dispatches(graph: &mut CallGraph, definitions: &Definitions) {
+    let mut implementations = definitions.traits.iter().collect::<Vec<_>>();
+    implementations.sort();
+    let mut methods = definitions.methods.iter().collect::<Vec<_>>();
+    methods.sort();
+    for (ty, traits) in implementations {
+        for trait_ in traits {
+            let prefix = format!("{trait_}::");
+            for method in &methods {
+                let Some(name) = method.strip_prefix(&prefix) else {
+                    continue;
+                };
+                let (Some(overridden), Some(implementation)) =
+                    (graph.find(method), graph.find(&format!("{ty}::{name}")))
+                else {
+                    continue;
+                };
+                let Some(location) = &graph.node(implementation).location else {
+                    continue;
+                };
+                let site = CallSite {
+                    receiver: None,
+                    line: location.start_line,
+                    column: 1,
+                    context: Context::default(),
+                };
+                graph.edges.push(Edge {
+                    caller: overridden,
+                    callee: implementation,
+                    kind: EdgeKind::Dispatch,
+                    sites: vec![site],
+                    count: None,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::generate_graph_from_path;
+
+    fn edge<'g>(graph: &'g CallGraph, caller: &str, callee: &str) -> &'g Edge {
+        graph
+            .edge(caller, callee)
+            .unwrap_or_else(|| panic!("no edge from {caller} to {callee}"))
+    }
+
+    #[test]
+    fn inventory() {
+        let graph =
+            generate_graph_from_path(Path::new("assets/inventory.rs"), &GraphOptions::default())
+                .unwrap();
+
+        // Through `use`, a `Self` constructor and a field of a known type
+        edge(&graph, "inventory::main", "inventory::report::Report::open");
+        edge(
+            &graph,
+            "inventory::report::Report::open",
+            "inventory::storage::Memory::new",
+        );
+        let get = edge(
+            &graph,
+            "inventory::report::Report::line",
+            "inventory::storage::Memory::get",
+        );
+        assert_eq!(get.kind, EdgeKind::AttributeCall);
+        assert_eq!(get.sites[0].receiver.as_deref(), Some("self.store"));
+        let format = edge(
+            &graph,
+            "inventory::report::Report::line",
+            "inventory::report::format_count",
+        );
+        assert!(format.sites[0].context.conditional);
+
+        // A trait method calls the implementations of the method it calls
+        edge(
+            &graph,
+            "inventory::storage::Store::contains",
+            "inventory::storage::Store::get",
+        );
+        let dispatch = edge(
+            &graph,
+            "inventory::storage::Store::get",
+            "inventory::storage::Memory::get",
+        );
+        assert_eq!(dispatch.kind, EdgeKind::Dispatch);
+
+        let line = edge(&graph, "inventory::main", "inventory::report::Report::line");
+        let context = line.sites[0].context;
+        assert!(context.in_loop && context.conditional);
+        let closure = edge(&graph, "inventory::main", "inventory::main::<closure@L65>");
+        assert_eq!(closure.kind, EdgeKind::Reference);
+        assert_eq!(graph.node(closure.callee).kind, NodeKind::Lambda);
+        edge(
+            &graph,
+            "inventory::main::<closure@L65>",
+            "inventory::report::Report::line",
+        );
+        let exit = edge(&graph, "inventory::main", "std::process::exit");
+        assert_eq!(graph.node(exit.callee).category, Category::Stdlib);
+        assert!(exit.sites[0].context.conditional);
+
+        // Receivers of an unknown type are kept apart by the type or function they are used in
+        edge(
+            &graph,
+            "inventory::storage::Memory::get",
+            "inventory::storage::Memory::<self.items.iter()>::find",
+        );
+        edge(&graph, "inventory::main", "inventory::main::<expr>::map");
+        // Going by the method name, this is a method of a standard type...
+        let is_empty = edge(
+            &graph,
+            "inventory::main",
+            "inventory::main::<keys>::is_empty",
+        );
+        assert_eq!(graph.node(is_empty.callee).category, Category::Builtin);
+        // ...and this one of the project, which defines a `get` method
+        let get = edge(
+            &graph,
+            "inventory::report::total::<closure@L54>",
+            "inventory::report::total::<closure@L54>::<store>::get",
+        );
+        assert_eq!(graph.node(get.callee).category, Category::Project);
+    }
+}
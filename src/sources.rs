@@ -0,0 +1,51 @@
+use glob::Pattern;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Decides which files of a directory are analyzed. Globs are matched against paths relative
+/// to the analyzed directory, e.g. `tests/**` or `**/venv/**`.
+#[derive(Debug, Default, Clone)]
+pub struct SourceFilter {
+    /// If not empty, only files matching one of these are analyzed
+    pub include: Vec<Pattern>,
+    pub exclude: Vec<Pattern>,
+}
+
+impl SourceFilter {
+    pub fn matches(&self, relative: &Path) -> bool {
+        let included =
+            self.include.is_empty() || self.include.iter().any(|glob| glob.matches_path(relative));
+        included && !self.exclude.iter().any(|glob| glob.matches_path(relative))
+    }
+}
+
+/// Finds the files with one of `extensions` under `path`, sorted so graphs are built in a
+/// stable order. A path to a single file is returned as-is, without being filtered.
+pub fn find_sources(
+    path: &Path,
+    extensions: &[&str],
+    filter: &SourceFilter,
+) -> walkdir::Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut sources = Vec::new();
+    for entry in WalkDir::new(path) {
+        let entry = entry?;
+        let is_source = entry.file_type().is_file()
+            && entry
+                .path()
+                .extension()
+                .is_some_and(|ext| extensions.iter().any(|&extension| ext == extension));
+        if !is_source {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(path).unwrap_or(entry.path());
+        if filter.matches(relative) {
+            sources.push(entry.into_path());
+        }
+    }
+    sources.sort();
+    Ok(sources)
+}
@@ -2,6 +2,7 @@ use std::{
     collections::HashMap,
     f32::consts::PI,
     path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
     time::Duration,
 };
 
@@ -18,12 +19,27 @@ use bevy::{
 };
 use bevy_prototype_lyon::{path, prelude::*};
 use bevy_tweening::{lens::ColorMaterialColorLens, *};
+use notify_debouncer_full::{
+    new_debouncer,
+    notify::{RecommendedWatcher, RecursiveMode, Watcher},
+    DebounceEventResult, Debouncer, FileIdMap,
+};
 use rand::Rng;
 
-use crate::generate_graph;
+use crate::{
+    call_graph::{Category, EdgeKind},
+    frontend::{self, GraphOptions},
+};
 
-pub fn init(watch: impl AsRef<Path>) {
-    App::new()
+pub fn init(watch: impl AsRef<Path>, options: GraphOptions) {
+    let mut app = App::new();
+    match SourceWatcher::new(watch.as_ref()) {
+        Ok(watcher) => {
+            app.insert_non_send_resource(watcher);
+        }
+        Err(error) => warn!("not reloading on changes: {error}"),
+    }
+    app
         // Plugins
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
@@ -36,19 +52,21 @@ pub fn init(watch: impl AsRef<Path>) {
         .add_plugins(TweeningPlugin)
         // Systems
         .add_systems(Startup, setup)
-        .add_systems(Update, draw_edges)
-        .add_systems(Update, load_graph)
+        // Edges are drawn after a reload so they never point at the old nodes
+        .add_systems(Update, (load_graph, draw_edges).chain())
+        .add_systems(Update, reload_on_change)
         .add_systems(Update, add_node_forces)
         .add_systems(Update, update_cursor_coords)
         .add_systems(Update, draggables)
         .add_systems(Update, move_draggable_locked)
         .add_systems(Update, graph_highlights)
         .add_systems(Update, highlight)
+        .add_systems(Update, toggle_hidden)
         .add_systems(Update, bevy::window::close_on_esc)
         // Events
         .add_event::<LoadGraph>()
         // Resources
-        .insert_resource(LoadPath(watch.as_ref().to_path_buf()))
+        .insert_resource(LoadPath(watch.as_ref().to_path_buf(), options))
         .insert_resource(CursorCoords::default())
         .insert_resource(NodeGraph::default())
         .insert_resource(ClearColor(Color::rgb_u8(25, 25, 35)))
@@ -58,8 +76,45 @@ pub fn init(watch: impl AsRef<Path>) {
 #[derive(Resource, Default)]
 struct CursorCoords(Vec2);
 
+/// Reloads the graph when a file under the loaded path changes
+struct SourceWatcher {
+    path: PathBuf,
+    _debouncer: Debouncer<RecommendedWatcher, FileIdMap>,
+    events: Receiver<DebounceEventResult>,
+}
+
+impl SourceWatcher {
+    fn new(path: &Path) -> notify_debouncer_full::notify::Result<Self> {
+        // Events come with absolute paths
+        let path = &path.canonicalize()?;
+        // Editors often save by replacing the file, which would end a watch on the file itself
+        let watched = match path.parent() {
+            Some(parent) if path.is_file() => parent,
+            _ => path,
+        };
+        let (sender, events) = mpsc::channel();
+        let mut debouncer = new_debouncer(Duration::from_millis(200), None, sender)?;
+        debouncer
+            .watcher()
+            .watch(watched, RecursiveMode::Recursive)?;
+        debouncer
+            .cache()
+            .add_root(watched, RecursiveMode::Recursive);
+        Ok(Self {
+            path: path.to_path_buf(),
+            _debouncer: debouncer,
+            events,
+        })
+    }
+}
+
+/// Shows why the graph couldn't be loaded, over the last graph that could
+#[derive(Component)]
+struct ErrorOverlay;
+
+/// The file or directory to load, and how to build its graph
 #[derive(Resource)]
-struct LoadPath(PathBuf);
+struct LoadPath(PathBuf, GraphOptions);
 
 #[derive(Component)]
 struct Node(NodeIndex, Vec<NodeIndex>);
@@ -72,8 +127,9 @@ struct Draggable {
 #[derive(Component)]
 struct DraggableLocked;
 
+/// A caller, a callee, how many times the call is made, how, and the color it's drawn in
 #[derive(Component)]
-struct Edge(NodeIndex, NodeIndex);
+struct Edge(NodeIndex, NodeIndex, usize, EdgeKind, Color);
 
 #[derive(Component)]
 struct Highlight(Color);
@@ -82,7 +138,7 @@ struct Highlight(Color);
 struct LoadGraph;
 
 #[derive(Resource, Default, Debug)]
-struct NodeGraph(Graph<Entity, ()>);
+struct NodeGraph(Graph<Entity, usize>);
 
 impl NodeGraph {
     fn get_node(&self, node: NodeIndex) -> Entity {
@@ -92,9 +148,57 @@ impl NodeGraph {
 
 fn setup(mut commands: Commands, mut ev_load_graph: EventWriter<LoadGraph>) {
     commands.spawn(Camera2dBundle::default());
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            left: Val::Px(10.),
+            padding: UiRect::all(Val::Px(8.)),
+            ..default()
+        })
+        .with_background_color(Color::rgba(0.6, 0.1, 0.1, 0.85)),
+        Visibility::Hidden,
+        ErrorOverlay,
+    ));
     ev_load_graph.send(LoadGraph);
 }
 
+fn reload_on_change(
+    watcher: Option<NonSend<SourceWatcher>>,
+    mut ev_load_graph: EventWriter<LoadGraph>,
+) {
+    let Some(watcher) = watcher else {
+        return;
+    };
+    let mut changed = false;
+    for result in watcher.events.try_iter() {
+        match result {
+            Ok(events) => {
+                changed |= events
+                    .iter()
+                    .flat_map(|event| &event.paths)
+                    .any(|path| path.starts_with(&watcher.path));
+            }
+            Err(errors) => errors.iter().for_each(|error| warn!("{error}")),
+        }
+    }
+    if changed {
+        ev_load_graph.send(LoadGraph);
+    }
+}
+
+/// Nodes and edges, which are replaced whenever the graph is loaded
+type GraphEntity = Or<(With<Node>, With<Edge>)>;
+
+#[allow(clippy::too_many_arguments)]
 fn load_graph(
     mut commands: Commands,
     mut ev_load_graph: EventReader<LoadGraph>,
@@ -104,18 +208,60 @@ fn load_graph(
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut res_graph: ResMut<NodeGraph>,
 
-    old_nodes: Query<Entity, With<Node>>,
+    old_nodes: Query<Entity, GraphEntity>,
+    mut overlay: Query<(&mut Text, &mut Visibility), With<ErrorOverlay>>,
 ) {
     for _ in ev_load_graph.read() {
+        // A file that doesn't parse leaves the last graph on screen until it's fixed
+        let (mut text, mut visibility) = overlay.single_mut();
+        let graph = match frontend::generate_graph_from_path(&load_path.0, &load_path.1) {
+            Ok(graph) => graph,
+            Err(error) => {
+                error!("{error}");
+                text.sections[0].value = error.to_string();
+                *visibility = Visibility::Visible;
+                continue;
+            }
+        };
+        // Files left out of a lenient load are listed over the rest of the graph
+        for error in &graph.errors {
+            error!("{error}");
+        }
+        text.sections[0].value = graph
+            .errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+        *visibility = if graph.errors.is_empty() {
+            Visibility::Hidden
+        } else {
+            Visibility::Visible
+        };
+
         old_nodes.for_each(|e| {
             commands.entity(e).despawn_recursive();
         });
-
-        let input = std::fs::read_to_string(&load_path.0).unwrap();
-        let graph = generate_graph::generate_graph(&input, &load_path.0.to_string_lossy()).unwrap();
+        res_graph.0.clear();
+
+        for ambiguity in &graph.ambiguities {
+            let caller = graph.node(ambiguity.caller);
+            let file = caller
+                .location
+                .as_ref()
+                .map_or_else(String::new, |location| location.file.display().to_string());
+            warn!(
+                "{file}:{}:{}: `{}` in `{}` could call any of {}",
+                ambiguity.site.line,
+                ambiguity.site.column,
+                ambiguity.call,
+                caller.name,
+                ambiguity.candidates.join(", ")
+            );
+        }
         let mut id_lookups = HashMap::new();
         let mut rng = rand::thread_rng();
-        for (i, node) in graph.keys().enumerate() {
+        for (i, (node_id, node)) in graph.nodes().enumerate() {
             let x = rng.gen_range((-250.)..250.);
             let y = rng.gen_range((-250.)..250.);
             let id = res_graph.0.add_node(
@@ -131,10 +277,23 @@ fn load_graph(
                         Draggable { hit_radius: 30. },
                     ))
                     .with_children(|parent| {
-                        let len = node.len();
+                        let len = node.name.len();
+                        // Nodes from a profile show how long was spent in them
+                        let mut label = match node.cumulative_time {
+                            Some(time) => format!("{}\n{:.2} ms", node.name, time * 1000.),
+                            None => node.name.clone(),
+                        };
+                        // Calls to hidden categories have no edges, but still count
+                        if node.hidden_calls > 0 {
+                            label += &format!(
+                                "\n{} calls, {} hidden",
+                                graph.fan_out(node_id),
+                                node.hidden_calls
+                            );
+                        }
                         parent.spawn(Text2dBundle {
                             text: Text::from_section(
-                                node,
+                                label,
                                 TextStyle {
                                     font_size: 50.,
                                     color: Color::WHITE,
@@ -148,18 +307,30 @@ fn load_graph(
                     })
                     .id(),
             );
-            id_lookups.insert(node, id);
+            id_lookups.insert(node_id, id);
         }
-        for (node, neighbors) in &graph {
-            let id = id_lookups[&node];
-            let neighbor_ids = neighbors.iter().map(|n| id_lookups[n]).collect::<Vec<_>>();
+        for (node_id, _) in graph.nodes() {
+            let id = id_lookups[&node_id];
+            let neighbor_ids = graph
+                .calls_from(node_id)
+                .map(|edge| id_lookups[&edge.callee])
+                .collect::<Vec<_>>();
             commands
                 .entity(*res_graph.0.node_weight(id).unwrap())
                 .insert(Node(id, neighbor_ids));
-            for neighbor in neighbors {
-                commands.spawn(Edge(id, id_lookups[neighbor]));
-                res_graph.0.add_edge(id, id_lookups[neighbor], ());
-            }
+        }
+        for edge in &graph.edges {
+            let (caller, callee) = (id_lookups[&edge.caller], id_lookups[&edge.callee]);
+            // Calls that may be skipped are drawn dimmer, calls only made on error in red
+            let color = if edge.is_error_only() {
+                Color::rgb(0.45, 0.2, 0.2)
+            } else if edge.is_unconditional() {
+                Color::DARK_GRAY
+            } else {
+                Color::rgb(0.17, 0.17, 0.2)
+            };
+            commands.spawn(Edge(caller, callee, edge.weight(), edge.kind, color));
+            res_graph.0.add_edge(caller, callee, edge.weight());
         }
     }
 }
@@ -174,7 +345,7 @@ fn draw_edges(
         .iter()
         .for_each(|(_, e)| commands.entity(e).despawn_recursive());
     for edge in edges.iter().map(|e| e.0) {
-        let color: Color = Color::DARK_GRAY;
+        let color = edge.4;
 
         if edge.0 == edge.1 {
             let node = nodes
@@ -204,9 +375,9 @@ fn draw_edges(
                             },
                             ..default()
                         },
-                        Stroke::new(color, 1.),
+                        Stroke::new(color, weight_factor(edge.2)),
                     ),
-                    Edge(edge.0, edge.1),
+                    Edge(edge.0, edge.1, edge.2, edge.3, edge.4),
                 ))
                 .with_children(|parent| {
                     parent.spawn((
@@ -254,7 +425,13 @@ fn draw_edges(
             .spawn((
                 (
                     ShapeBundle {
-                        path: GeometryBuilder::build_as(&line),
+                        // Functions that are only passed around, and calls through
+                        // pointers that may go elsewhere, are drawn dashed
+                        path: if matches!(edge.3, EdgeKind::Reference | EdgeKind::Indirect) {
+                            dashed_line(line.0, line.1)
+                        } else {
+                            GeometryBuilder::build_as(&line)
+                        },
                         spatial: SpatialBundle {
                             // Lines should be drawn behind nodes
                             transform: Transform::from_xyz(0., 0., -1.),
@@ -263,9 +440,9 @@ fn draw_edges(
                         ..default()
                     },
                     Fill::color(color),
-                    Stroke::new(color, 1.),
+                    Stroke::new(color, weight_factor(edge.2)),
                 ),
-                Edge(edge.0, edge.1),
+                Edge(edge.0, edge.1, edge.2, edge.3, edge.4),
             ))
             .with_children(|parent| {
                 parent.spawn((
@@ -309,7 +486,7 @@ fn add_node_forces(
             head_transform.translation.truncate(),
             STRENGTH,
         );
-        head_transform.translation += (force * 2.).extend(0.);
+        head_transform.translation += (force * 2. * weight_factor(edge.2)).extend(0.);
     }
     for edge in &edges {
         if edge.0 == edge.1 {
@@ -321,7 +498,7 @@ fn add_node_forces(
         let tail_pos = nodes.get_component::<Transform>(tail).unwrap().translation;
         let mut t1 = nodes.get_component_mut::<Transform>(head).unwrap();
         let force = calc_force(tail_pos.truncate(), t1.translation.truncate(), STRENGTH);
-        t1.translation += force.extend(0.);
+        t1.translation += (force * weight_factor(edge.2)).extend(0.);
     }
 
     // Apply weak repulsion between nodes
@@ -338,6 +515,26 @@ fn add_node_forces(
     }
 }
 
+fn dashed_line(from: Vec2, to: Vec2) -> bevy_prototype_lyon::prelude::Path {
+    const DASH: f32 = 8.;
+    let length = from.distance(to);
+    let direction = (to - from) / length;
+    let mut path = path::PathBuilder::new();
+    let mut start = 0.;
+    while start < length {
+        path.move_to(from + direction * start);
+        path.line_to(from + direction * (start + DASH).min(length));
+        start += DASH * 2.;
+    }
+    path.build()
+}
+
+/// Scales edge thickness and attraction with how many times a call is made, growing slowly
+/// so that hot edges don't pull everything into one spot.
+fn weight_factor(weight: usize) -> f32 {
+    1. + (weight.max(1) as f32).ln()
+}
+
 fn calc_force(p: Vec2, q: Vec2, strength: f32) -> Vec2 {
     let diff = p - q;
     let dist = diff.length();
@@ -419,6 +616,38 @@ fn update_cursor_coords(
     cursor_coords.0 = world_position;
 }
 
+fn toggle_hidden(
+    keys: Res<Input<KeyCode>>,
+    mut load_path: ResMut<LoadPath>,
+    mut ev_load_graph: EventWriter<LoadGraph>,
+) {
+    let toggled = [
+        (KeyCode::B, Category::Builtin),
+        (KeyCode::S, Category::Stdlib),
+        (KeyCode::T, Category::ThirdParty),
+        (KeyCode::P, Category::Project),
+    ]
+    .into_iter()
+    .filter(|(key, _)| keys.just_pressed(*key))
+    .map(|(_, category)| category)
+    .collect::<Vec<_>>();
+    let toggled_module_scope = keys.just_pressed(KeyCode::M);
+    if toggled.is_empty() && !toggled_module_scope {
+        return;
+    }
+
+    let options = &mut load_path.1;
+    for category in toggled {
+        if !options.hidden.remove(&category) {
+            options.hidden.insert(category);
+        }
+    }
+    if toggled_module_scope {
+        options.exclude_module_scope = !options.exclude_module_scope;
+    }
+    ev_load_graph.send(LoadGraph);
+}
+
 fn graph_highlights(mut commands: Commands, keys: Res<Input<KeyCode>>, graph: Res<NodeGraph>) {
     let any_pressed = keys.just_pressed(KeyCode::R)
         || keys.just_pressed(KeyCode::Key1)